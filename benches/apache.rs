@@ -126,3 +126,55 @@ fn no_match_end_anchor(b: divan::Bencher) {
         }
     });
 }
+
+#[divan::bench]
+fn is_match(b: divan::Bencher) {
+    let msg = r#"220.181.108.96 - - [13/Jun/2015:21:14:28 +0000] "GET /blog/geekery/xvfb-firefox.html HTTP/1.1" 200 10975 "-" "Mozilla/5.0 (compatible; Baiduspider/2.0; +http://www.baidu.com/search/spider.html)""#;
+
+    let grok = Grok::default();
+    let pattern = grok.compile(r#"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "%{WORD:verb} %{DATA:request} HTTP/%{NUMBER:httpversion}" %{NUMBER:response} %{NUMBER:bytes} %{QS:referrer} %{QS:agent}"#, false)
+        .expect("Error while compiling!");
+
+    b.bench(|| {
+        divan::black_box(pattern.is_match(msg));
+    });
+}
+
+#[divan::bench]
+fn no_match_end_is_match(b: divan::Bencher) {
+    let msg = r#"220.181.108.96 - - [13/Jun/2015:21:14:28 +0000] "GET /blog/geekery/xvfb-firefox.html HTTP/1.1" 200 10975 "-" 1"#;
+
+    let grok = Grok::default();
+    let pattern = grok.compile(r#"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "%{WORD:verb} %{DATA:request} HTTP/%{NUMBER:httpversion}" %{NUMBER:response} %{NUMBER:bytes} %{QS:referrer} %{QS:agent}"#, false)
+        .expect("Error while compiling!");
+
+    b.bench(|| {
+        divan::black_box(pattern.is_match(msg));
+    });
+}
+
+#[divan::bench]
+fn find(b: divan::Bencher) {
+    let msg = r#"220.181.108.96 - - [13/Jun/2015:21:14:28 +0000] "GET /blog/geekery/xvfb-firefox.html HTTP/1.1" 200 10975 "-" "Mozilla/5.0 (compatible; Baiduspider/2.0; +http://www.baidu.com/search/spider.html)""#;
+
+    let grok = Grok::default();
+    let pattern = grok.compile(r#"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "%{WORD:verb} %{DATA:request} HTTP/%{NUMBER:httpversion}" %{NUMBER:response} %{NUMBER:bytes} %{QS:referrer} %{QS:agent}"#, false)
+        .expect("Error while compiling!");
+
+    b.bench(|| {
+        divan::black_box(pattern.find(msg));
+    });
+}
+
+#[divan::bench]
+fn no_match_end_find(b: divan::Bencher) {
+    let msg = r#"220.181.108.96 - - [13/Jun/2015:21:14:28 +0000] "GET /blog/geekery/xvfb-firefox.html HTTP/1.1" 200 10975 "-" 1"#;
+
+    let grok = Grok::default();
+    let pattern = grok.compile(r#"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "%{WORD:verb} %{DATA:request} HTTP/%{NUMBER:httpversion}" %{NUMBER:response} %{NUMBER:bytes} %{QS:referrer} %{QS:agent}"#, false)
+        .expect("Error while compiling!");
+
+    b.bench(|| {
+        divan::black_box(pattern.find(msg));
+    });
+}
@@ -1,7 +1,9 @@
 extern crate glob;
 
 use glob::glob;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::ffi::OsString;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
@@ -9,10 +11,12 @@ use std::io::BufReader;
 use std::iter;
 use std::path::Path;
 
-fn main() {
-    let mut output = String::new();
+/// The `(name, definition, file stem)` of a pattern.
+type Line = (String, String, OsString);
 
-    let mut lines = glob("patterns/*.pattern")
+/// Loads the `Line` for every pattern in the files matching `pattern`.
+fn load_patterns(pattern: &str) -> Vec<Line> {
+    let mut lines = glob(pattern)
         .unwrap() // load filepaths
         // extract the filepath
         .map(|e| e.unwrap())
@@ -37,45 +41,52 @@ fn main() {
         })
         .collect::<Vec<_>>();
     lines.sort();
+    lines
+}
 
+/// Writes a `Cow` static table for a set of patterns.
+fn write_cow_table(output: &mut String, name: &str, lines: &[&Line]) {
     fmt::write(
-        &mut output,
-        format_args!("static PATTERNS: &[(&str, &str)] = &[\n"),
+        output,
+        format_args!("static {name}: &[(Cow<'static, str>, Cow<'static, str>)] = &[\n"),
     )
     .unwrap();
 
-    for (key, value, _) in &lines {
+    for (key, value, _) in lines {
         fmt::write(
-            &mut output,
-            format_args!("\t(\"{}\", r#\"{}\"#),\n", key, value),
+            output,
+            format_args!(
+                "\t(Cow::Borrowed(\"{}\"), Cow::Borrowed(r#\"{}\"#)),\n",
+                key, value
+            ),
         )
         .unwrap();
     }
 
-    fmt::write(&mut output, format_args!("];\n")).unwrap();
+    fmt::write(output, format_args!("];\n")).unwrap();
+}
 
+/// Writes the `&str` and `Cow` static tables for a set of patterns.
+fn write_tables(output: &mut String, name: &str, lines: &[&Line]) {
     fmt::write(
-        &mut output,
-        format_args!("use std::borrow::Cow;\nstatic PATTERNS_COW: &[(Cow<'static, str>, Cow<'static, str>)] = &[\n"),
+        output,
+        format_args!("static {name}: &[(&str, &str)] = &[\n"),
     )
     .unwrap();
 
-    for (key, value, _) in &lines {
-        fmt::write(
-            &mut output,
-            format_args!(
-                "\t(Cow::Borrowed(\"{}\"), Cow::Borrowed(r#\"{}\"#)),\n",
-                key, value
-            ),
-        )
-        .unwrap();
+    for (key, value, _) in lines {
+        fmt::write(output, format_args!("\t(\"{}\", r#\"{}\"#),\n", key, value)).unwrap();
     }
 
-    fmt::write(&mut output, format_args!("];\n")).unwrap();
+    fmt::write(output, format_args!("];\n")).unwrap();
 
+    write_cow_table(output, &format!("{name}_COW"), lines);
+}
+
+/// Writes one documentation module per pattern file.
+fn write_modules(output: &mut String, lines: &mut [Line]) {
     lines.sort_by(|l1, l2| l1.2.cmp(&l2.2));
 
-    fmt::write(&mut output, format_args!("#[doc=include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/patterns/README.md\"))] \npub mod patterns {{\n")).unwrap();
     for chunk in lines.chunk_by(|l1, l2| l1.2 == l2.2) {
         let name = chunk
             .first()
@@ -84,16 +95,277 @@ fn main() {
             .to_string_lossy()
             .to_string()
             .replace('-', "_");
-        fmt::write(&mut output, format_args!("\npub mod {name} {{\n")).unwrap();
+        fmt::write(output, format_args!("\npub mod {name} {{\n")).unwrap();
         for (key, value, _) in chunk {
             fmt::write(
-                &mut output,
+                output,
                 format_args!("#[doc=r#\"`{value}`\"#] pub const {key}: &str = r#\"{value}\"#;\n"),
             )
             .unwrap();
         }
-        fmt::write(&mut output, format_args!("\n}}\n")).unwrap();
+        fmt::write(output, format_args!("\n}}\n")).unwrap();
+    }
+}
+
+/// Writes a test-only table of the contents of every pattern file matching
+/// `pattern` whose group is enabled, so their embedded tests can be run.
+fn write_sources(output: &mut String, name: &str, pattern: &str, enabled: &BTreeSet<String>) {
+    fmt::write(
+        output,
+        format_args!("#[cfg(test)]\nstatic {name}: &[(&str, &str)] = &[\n"),
+    )
+    .unwrap();
+
+    for path in glob(pattern).unwrap().map(|e| e.unwrap()) {
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        if !enabled.contains(stem.as_ref()) {
+            continue;
+        }
+        fmt::write(
+            output,
+            format_args!(
+                "\t(\"{}\", include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/{}\"))),\n",
+                stem,
+                path.to_string_lossy()
+            ),
+        )
+        .unwrap();
+    }
+
+    fmt::write(output, format_args!("];\n")).unwrap();
+}
+
+/// Writes the documentation modules for a pattern set, followed by a nested
+/// `linear` module for its linear-time replacements.
+fn write_set_modules(
+    output: &mut String,
+    dir: &str,
+    lines: &mut [Line],
+    linear_lines: &mut [Line],
+) {
+    write_modules(output, lines);
+
+    fmt::write(output, format_args!("\n#[doc=include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/{dir}/linear/README.md\"))] \npub mod linear {{\n")).unwrap();
+    write_modules(output, linear_lines);
+    fmt::write(output, format_args!("\n}}\n")).unwrap();
+}
+
+/// Converts a file stem such as `linux-syslog` to `LinuxSyslog`.
+fn variant_name(stem: &str) -> String {
+    stem.split(['-', '_'])
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .unwrap()
+                .to_uppercase()
+                .chain(chars)
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Converts a file stem such as `linux-syslog` to `LINUX_SYSLOG`.
+fn constant_name(stem: &str) -> String {
+    stem.to_uppercase().replace('-', "_")
+}
+
+/// Returns the groups that the patterns of each group refer to, not including
+/// the group itself.
+fn group_dependencies(lines: &[Line]) -> BTreeMap<String, BTreeSet<String>> {
+    let groups = lines
+        .iter()
+        .map(|(key, _, stem)| (key.as_str(), stem.to_string_lossy().to_string()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut dependencies = BTreeMap::<String, BTreeSet<String>>::new();
+    for (_, value, stem) in lines {
+        let stem = stem.to_string_lossy().to_string();
+        let entry = dependencies.entry(stem.clone()).or_default();
+        for reference in value.split("%{").skip(1) {
+            let end = reference
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(reference.len());
+            if let Some(group) = groups.get(&reference[..end]) {
+                if *group != stem {
+                    entry.insert(group.clone());
+                }
+            }
+        }
+    }
+    dependencies
+}
+
+/// Returns the groups that are compiled in: every group, unless the
+/// `select-patterns` feature is enabled, in which case only the groups whose
+/// `patterns-<group>` feature is enabled, and the groups they depend on.
+fn enabled_groups(dependencies: &BTreeMap<String, BTreeSet<String>>) -> BTreeSet<String> {
+    if env::var_os("CARGO_FEATURE_SELECT_PATTERNS").is_none() {
+        return dependencies.keys().cloned().collect();
     }
+
+    let mut enabled = BTreeSet::new();
+    let mut pending = dependencies
+        .keys()
+        .filter(|stem| {
+            env::var_os(format!("CARGO_FEATURE_PATTERNS_{}", constant_name(stem))).is_some()
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    while let Some(stem) = pending.pop() {
+        if enabled.insert(stem.clone()) {
+            pending.extend(dependencies[&stem].iter().cloned());
+        }
+    }
+    enabled
+}
+
+/// Writes the `PatternGroup` enum, with one variant per pattern file, and the
+/// per-group tables that back it.
+fn write_groups(
+    output: &mut String,
+    dependencies: &BTreeMap<String, BTreeSet<String>>,
+    enabled: &BTreeSet<String>,
+    sets: &[(&str, &[Line])],
+) {
+    for stem in dependencies.keys() {
+        for (set, lines) in sets {
+            let group_lines = lines
+                .iter()
+                .filter(|line| enabled.contains(stem) && line.2.to_string_lossy() == *stem)
+                .collect::<Vec<_>>();
+            write_cow_table(
+                output,
+                &format!("GROUP_{}{set}_COW", constant_name(stem)),
+                &group_lines,
+            );
+        }
+    }
+
+    fmt::write(output, format_args!("\n/// A group of built-in patterns, defined by one pattern file.\n///\n/// See [`Grok::with_pattern_groups`] for more information.\n#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]\n#[non_exhaustive]\npub enum PatternGroup {{\n")).unwrap();
+    for stem in dependencies.keys() {
+        fmt::write(
+            output,
+            format_args!(
+                "\t/// The patterns of `{stem}.pattern`.\n\t{},\n",
+                variant_name(stem)
+            ),
+        )
+        .unwrap();
+    }
+    fmt::write(output, format_args!("}}\n")).unwrap();
+
+    fmt::write(output, format_args!("\nimpl PatternGroup {{\n\t/// Every group, in alphabetical order.\n\tpub const ALL: &'static [PatternGroup] = &[\n")).unwrap();
+    for stem in dependencies.keys() {
+        fmt::write(
+            output,
+            format_args!("\t\tPatternGroup::{},\n", variant_name(stem)),
+        )
+        .unwrap();
+    }
+    fmt::write(
+        output,
+        format_args!("\t];\n\n\tfn tables(self) -> GroupTables {{\n\t\tmatch self {{\n"),
+    )
+    .unwrap();
+    for (stem, group_dependencies) in dependencies {
+        let constant = constant_name(stem);
+        fmt::write(
+            output,
+            format_args!(
+                "\t\t\tPatternGroup::{} => GroupTables {{\n\t\t\t\tname: \"{stem}\",\n\t\t\t\tnamespace: \"{}\",\n\t\t\t\tenabled: {},\n\t\t\t\tdependencies: &[{}],\n",
+                variant_name(stem),
+                stem.replace('-', "_"),
+                enabled.contains(stem),
+                group_dependencies
+                    .iter()
+                    .map(|d| format!("PatternGroup::{}", variant_name(d)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        )
+        .unwrap();
+        for (set, _) in sets {
+            fmt::write(
+                output,
+                format_args!(
+                    "\t\t\t\t{}: GROUP_{constant}{set}_COW,\n",
+                    if set.is_empty() {
+                        "legacy".to_string()
+                    } else {
+                        set.trim_start_matches('_').to_lowercase()
+                    }
+                ),
+            )
+            .unwrap();
+        }
+        fmt::write(output, format_args!("\t\t\t}},\n")).unwrap();
+    }
+    fmt::write(output, format_args!("\t\t}}\n\t}}\n}}\n")).unwrap();
+}
+
+fn main() {
+    let mut output = String::new();
+
+    let mut lines = load_patterns("patterns/*.pattern");
+    let mut linear_lines = load_patterns("patterns/linear/*.pattern");
+    let mut ecs_lines = load_patterns("patterns/ecs-v1/*.pattern");
+    let mut ecs_linear_lines = load_patterns("patterns/ecs-v1/linear/*.pattern");
+
+    let dependencies = group_dependencies(&lines);
+    let enabled = enabled_groups(&dependencies);
+    let is_enabled = |line: &&Line| enabled.contains(line.2.to_string_lossy().as_ref());
+
+    fmt::write(&mut output, format_args!("use std::borrow::Cow;\n")).unwrap();
+    write_tables(
+        &mut output,
+        "PATTERNS",
+        &lines.iter().filter(is_enabled).collect::<Vec<_>>(),
+    );
+    write_tables(
+        &mut output,
+        "LINEAR_PATTERNS",
+        &linear_lines.iter().filter(is_enabled).collect::<Vec<_>>(),
+    );
+    write_tables(
+        &mut output,
+        "ECS_V1_PATTERNS",
+        &ecs_lines.iter().filter(is_enabled).collect::<Vec<_>>(),
+    );
+    write_tables(
+        &mut output,
+        "ECS_V1_LINEAR_PATTERNS",
+        &ecs_linear_lines
+            .iter()
+            .filter(is_enabled)
+            .collect::<Vec<_>>(),
+    );
+    write_groups(
+        &mut output,
+        &dependencies,
+        &enabled,
+        &[("", &lines), ("_ECS_V1", &ecs_lines)],
+    );
+    write_sources(&mut output, "PATTERN_FILES", "patterns/*.pattern", &enabled);
+    write_sources(
+        &mut output,
+        "ECS_V1_PATTERN_FILES",
+        "patterns/ecs-v1/*.pattern",
+        &enabled,
+    );
+
+    fmt::write(&mut output, format_args!("#[doc=include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/patterns/README.md\"))] \npub mod patterns {{\n")).unwrap();
+    write_set_modules(&mut output, "patterns", &mut lines, &mut linear_lines);
+
+    fmt::write(&mut output, format_args!("\n#[doc=include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/patterns/ecs-v1/README.md\"))] \npub mod ecs_v1 {{\n")).unwrap();
+    write_set_modules(
+        &mut output,
+        "patterns/ecs-v1",
+        &mut ecs_lines,
+        &mut ecs_linear_lines,
+    );
+    fmt::write(&mut output, format_args!("\n}}\n")).unwrap();
+
     fmt::write(&mut output, format_args!("\n}}\n")).unwrap();
 
     let out_dir = env::var("OUT_DIR").unwrap();
@@ -0,0 +1,888 @@
+//! A syntax tree of grok patterns, covering both the placeholders and the
+//! regular expression around them.
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::pattern_parser::{grok_split, GrokComponent, GrokPatternError};
+
+/// A parsed grok pattern.
+///
+/// Printing an `Ast` with `Display` gives back the text it was parsed from,
+/// including any changes made by a [`Rewriter`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ast<'a> {
+    /// The nodes of the pattern, in order.
+    pub nodes: Vec<Node<'a>>,
+}
+
+/// A node of a grok pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node<'a> {
+    /// Literal text, including escaped punctuation such as `\.`.
+    Literal(Cow<'a, str>),
+    /// An escape sequence that is not a literal character, such as `\d`, `\b`
+    /// or `\x25`.
+    Escape(Cow<'a, str>),
+    /// One of `.`, `^` or `$`.
+    Meta(Cow<'a, str>),
+    /// A bracketed character class, such as `[a-z]` or `[^[:space:]]`.
+    Class(Cow<'a, str>),
+    /// A group, such as `(...)`, `(?:...)` or `(?<=...)`.
+    Group(Group<'a>),
+    /// Two or more alternatives separated by `|`.
+    Alternation(Vec<Vec<Node<'a>>>),
+    /// A repeated node, such as `a*`, `(?:ab)+?` or `\d{2,3}`.
+    Repeat {
+        /// The node that is repeated.
+        node: Box<Node<'a>>,
+        /// The quantifier, including a lazy or possessive suffix.
+        quantifier: Cow<'a, str>,
+    },
+    /// A grok placeholder, such as `%{WORD:name}`.
+    Placeholder(Placeholder<'a>),
+    /// A `\%{` escape, which stands for a literal `%{`.
+    EscapedPlaceholder,
+}
+
+/// A group of a regular expression.
+///
+/// A group is printed as its opening, its nodes and a closing parenthesis.
+/// Groups that only set flags, such as `(?i)`, and comments have no nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group<'a> {
+    /// The kind of group.
+    pub kind: GroupKind<'a>,
+    /// The opening of the group, such as `(`, `(?<name>` or `(?i:`.
+    pub open: Cow<'a, str>,
+    /// The nodes inside the group.
+    pub nodes: Vec<Node<'a>>,
+}
+
+/// The kind of a [`Group`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupKind<'a> {
+    /// A capturing group: `(...)`.
+    Capturing,
+    /// A named capturing group: `(?<name>...)`, `(?P<name>...)` or
+    /// `(?'name'...)`.
+    Named(Cow<'a, str>),
+    /// A non-capturing group: `(?:...)`.
+    NonCapturing,
+    /// A group that changes flags for its contents, `(?i:...)`, or for the
+    /// rest of the enclosing group, `(?i)`.
+    Flags(Cow<'a, str>),
+    /// A look-ahead assertion: `(?=...)`.
+    LookAhead,
+    /// A negative look-ahead assertion: `(?!...)`.
+    NegativeLookAhead,
+    /// A look-behind assertion: `(?<=...)`.
+    LookBehind,
+    /// A negative look-behind assertion: `(?<!...)`.
+    NegativeLookBehind,
+    /// An atomic group: `(?>...)`.
+    Atomic,
+    /// A comment: `(?#...)`.
+    Comment,
+}
+
+/// A grok placeholder of the form `%{name:alias:extract=definition}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placeholder<'a> {
+    /// The name of the pattern, possibly qualified by a namespace.
+    pub name: Cow<'a, str>,
+    /// The alias of the pattern, or an empty string.
+    pub alias: Cow<'a, str>,
+    /// The extract of the pattern, or an empty string.
+    pub extract: Cow<'a, str>,
+    /// The inline definition of the pattern, if any.
+    pub definition: Option<Ast<'a>>,
+    /// The definition the placeholder refers to, once resolved by
+    /// [`Grok::parse`](crate::Grok::parse).
+    pub resolved: Option<Box<Ast<'a>>>,
+}
+
+/// An error while parsing a grok pattern into an [`Ast`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset of the error in the pattern.
+    pub offset: usize,
+    /// The kind of error.
+    pub kind: ParseErrorKind,
+}
+
+/// The kind of a [`ParseError`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A placeholder could not be parsed. The range of the error is within
+    /// the whole pattern.
+    Placeholder(GrokPatternError),
+    /// A group is not closed.
+    UnclosedGroup,
+    /// A closing parenthesis has no matching group.
+    UnmatchedParenthesis,
+    /// A group starts with `(?` followed by an unknown construct.
+    UnknownGroup,
+    /// A character class is not closed.
+    UnclosedClass,
+    /// A quantifier does not follow anything that can be repeated.
+    NothingToRepeat,
+    /// The pattern ends with a backslash.
+    TrailingBackslash,
+}
+
+impl StdError for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Placeholder(e) => write!(f, "{e}"),
+            ParseErrorKind::UnclosedGroup => write!(f, "unclosed group"),
+            ParseErrorKind::UnmatchedParenthesis => write!(f, "unmatched closing parenthesis"),
+            ParseErrorKind::UnknownGroup => write!(f, "unknown group construct"),
+            ParseErrorKind::UnclosedClass => write!(f, "unclosed character class"),
+            ParseErrorKind::NothingToRepeat => write!(f, "quantifier has nothing to repeat"),
+            ParseErrorKind::TrailingBackslash => write!(f, "trailing backslash"),
+        }?;
+        write!(f, " at offset {}", self.offset)
+    }
+}
+
+/// Parses a grok pattern into an [`Ast`].
+///
+/// ```
+/// use grok::parser::{parse, Node};
+///
+/// let ast = parse(r"%{WORD:verb} (?:%{URIPATH:path})?").unwrap();
+/// assert!(matches!(ast.nodes[0], Node::Placeholder(_)));
+/// assert_eq!(r"%{WORD:verb} (?:%{URIPATH:path})?", ast.to_string());
+/// ```
+pub fn parse(pattern: &str) -> Result<Ast<'_>, ParseError> {
+    Parser::new(pattern, 0).parse()
+}
+
+/// A visitor of the nodes of an [`Ast`].
+///
+/// The default implementation visits every node in order, including the
+/// inline definitions of placeholders, but not the definitions they resolve
+/// to.
+///
+/// ```
+/// use grok::parser::{parse, visit_children, Node, Visitor};
+///
+/// struct Names(Vec<String>);
+///
+/// impl<'a> Visitor<'a> for Names {
+///     fn visit(&mut self, node: &Node<'a>) {
+///         if let Node::Placeholder(placeholder) = node {
+///             self.0.push(placeholder.name.to_string());
+///         }
+///         visit_children(self, node);
+///     }
+/// }
+///
+/// let mut names = Names(vec![]);
+/// parse("%{A} (%{B}|%{C=%{D}})").unwrap().visit(&mut names);
+/// assert_eq!(vec!["A", "B", "C", "D"], names.0);
+/// ```
+pub trait Visitor<'a> {
+    /// Visits a node.
+    fn visit(&mut self, node: &Node<'a>) {
+        visit_children(self, node);
+    }
+}
+
+/// Visits the children of a node, see [`Visitor`].
+pub fn visit_children<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, node: &Node<'a>) {
+    match node {
+        Node::Group(group) => group.nodes.iter().for_each(|node| visitor.visit(node)),
+        Node::Alternation(alternatives) => alternatives
+            .iter()
+            .flatten()
+            .for_each(|node| visitor.visit(node)),
+        Node::Repeat { node, .. } => visitor.visit(node),
+        Node::Placeholder(Placeholder {
+            definition: Some(definition),
+            ..
+        }) => definition.nodes.iter().for_each(|node| visitor.visit(node)),
+        _ => {}
+    }
+}
+
+/// A rewriter of the nodes of an [`Ast`].
+///
+/// The default implementation rewrites the children of every node, including
+/// the inline definitions of placeholders.
+///
+/// ```
+/// use grok::parser::{parse, rewrite_children, Node, Rewriter};
+///
+/// struct Rename;
+///
+/// impl<'a> Rewriter<'a> for Rename {
+///     fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+///         match rewrite_children(self, node) {
+///             Node::Placeholder(mut placeholder) if placeholder.name == "IP" => {
+///                 placeholder.name = "IPV4".into();
+///                 Node::Placeholder(placeholder)
+///             }
+///             node => node,
+///         }
+///     }
+/// }
+///
+/// let ast = parse("%{IP:client} (%{IP:server})?").unwrap().rewrite(&mut Rename);
+/// assert_eq!("%{IPV4:client} (%{IPV4:server})?", ast.to_string());
+/// ```
+pub trait Rewriter<'a> {
+    /// Rewrites a node.
+    fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+        rewrite_children(self, node)
+    }
+}
+
+/// Rewrites the children of a node, see [`Rewriter`].
+pub fn rewrite_children<'a, R: Rewriter<'a> + ?Sized>(
+    rewriter: &mut R,
+    node: Node<'a>,
+) -> Node<'a> {
+    let mut rewrite_all = |nodes: Vec<Node<'a>>| {
+        nodes
+            .into_iter()
+            .map(|node| rewriter.rewrite(node))
+            .collect::<Vec<_>>()
+    };
+    match node {
+        Node::Group(group) => Node::Group(Group {
+            nodes: rewrite_all(group.nodes),
+            ..group
+        }),
+        Node::Alternation(alternatives) => {
+            Node::Alternation(alternatives.into_iter().map(rewrite_all).collect())
+        }
+        Node::Repeat { node, quantifier } => Node::Repeat {
+            node: Box::new(rewriter.rewrite(*node)),
+            quantifier,
+        },
+        Node::Placeholder(Placeholder {
+            definition: Some(definition),
+            name,
+            alias,
+            extract,
+            resolved,
+        }) => Node::Placeholder(Placeholder {
+            definition: Some(Ast {
+                nodes: rewrite_all(definition.nodes),
+            }),
+            name,
+            alias,
+            extract,
+            resolved,
+        }),
+        node => node,
+    }
+}
+
+impl<'a> Ast<'a> {
+    /// Visits the nodes of this pattern.
+    pub fn visit<V: Visitor<'a> + ?Sized>(&self, visitor: &mut V) {
+        self.nodes.iter().for_each(|node| visitor.visit(node));
+    }
+
+    /// Rewrites the nodes of this pattern.
+    pub fn rewrite<R: Rewriter<'a> + ?Sized>(self, rewriter: &mut R) -> Ast<'a> {
+        Ast {
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|node| rewriter.rewrite(node))
+                .collect(),
+        }
+    }
+
+    /// Calls `f` with every placeholder of this pattern, in order, without
+    /// descending into inline definitions or resolved definitions.
+    pub(crate) fn for_each_placeholder_mut<E>(
+        &mut self,
+        f: &mut impl FnMut(&mut Placeholder<'a>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        fn walk<'a, E>(
+            nodes: &mut [Node<'a>],
+            f: &mut impl FnMut(&mut Placeholder<'a>) -> Result<(), E>,
+        ) -> Result<(), E> {
+            for node in nodes {
+                match node {
+                    Node::Group(group) => walk(&mut group.nodes, f)?,
+                    Node::Alternation(alternatives) => {
+                        for alternative in alternatives {
+                            walk(alternative, f)?;
+                        }
+                    }
+                    Node::Repeat { node, .. } => walk(std::slice::from_mut(&mut **node), f)?,
+                    Node::Placeholder(placeholder) => f(placeholder)?,
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+        walk(&mut self.nodes, f)
+    }
+}
+
+impl fmt::Display for Ast<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.nodes.iter().try_for_each(|node| write!(f, "{node}"))
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Literal(s) | Node::Escape(s) | Node::Meta(s) | Node::Class(s) => f.write_str(s),
+            Node::Group(group) => {
+                f.write_str(&group.open)?;
+                group
+                    .nodes
+                    .iter()
+                    .try_for_each(|node| write!(f, "{node}"))?;
+                f.write_str(")")
+            }
+            Node::Alternation(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    alternative
+                        .iter()
+                        .try_for_each(|node| write!(f, "{node}"))?;
+                }
+                Ok(())
+            }
+            Node::Repeat { node, quantifier } => write!(f, "{node}{quantifier}"),
+            Node::Placeholder(placeholder) => write!(f, "{placeholder}"),
+            Node::EscapedPlaceholder => f.write_str(r"\%{"),
+        }
+    }
+}
+
+impl fmt::Display for Placeholder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{{{}", self.name)?;
+        if !self.alias.is_empty() || !self.extract.is_empty() {
+            write!(f, ":{}", self.alias)?;
+        }
+        if !self.extract.is_empty() {
+            write!(f, ":{}", self.extract)?;
+        }
+        if let Some(definition) = &self.definition {
+            write!(f, "={definition}")?;
+        }
+        f.write_str("}")
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    /// The offset of `source` in the pattern, for error offsets.
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str, offset: usize) -> Self {
+        Self {
+            source,
+            pos: 0,
+            offset,
+        }
+    }
+
+    fn parse(mut self) -> Result<Ast<'a>, ParseError> {
+        let nodes = self.alternation(0)?;
+        Ok(Ast { nodes })
+    }
+
+    fn error(&self, pos: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            offset: self.offset + pos,
+            kind,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Parses alternatives up to the end of the enclosing group.
+    fn alternation(&mut self, depth: usize) -> Result<Vec<Node<'a>>, ParseError> {
+        let mut alternatives = vec![];
+        let mut sequence = self.sequence()?;
+        while let Some(next) = self.peek() {
+            match next {
+                '|' => {
+                    self.pos += 1;
+                    alternatives.push(sequence);
+                    sequence = self.sequence()?;
+                }
+                ')' if depth == 0 => {
+                    return Err(self.error(self.pos, ParseErrorKind::UnmatchedParenthesis))
+                }
+                _ => break,
+            }
+        }
+        if alternatives.is_empty() {
+            Ok(sequence)
+        } else {
+            alternatives.push(sequence);
+            Ok(vec![Node::Alternation(alternatives)])
+        }
+    }
+
+    /// Parses nodes up to the next `|` or `)`, merging consecutive literals.
+    fn sequence(&mut self) -> Result<Vec<Node<'a>>, ParseError> {
+        let mut nodes = vec![];
+        let mut literal: Option<usize> = None;
+        while let Some(next) = self.peek() {
+            if next == '|' || next == ')' {
+                break;
+            }
+            let start = self.pos;
+            let atom = self.atom()?;
+            let quantified = self.quantifier_len().is_some();
+            if matches!(atom, Node::Literal(_)) && !quantified {
+                literal.get_or_insert(start);
+                continue;
+            }
+            if let Some(literal_start) = literal.take() {
+                nodes.push(Node::Literal(self.source[literal_start..start].into()));
+            }
+            let mut node = atom;
+            while let Some(len) = self.quantifier_len() {
+                let quantifier = &self.rest()[..len];
+                self.pos += len;
+                node = Node::Repeat {
+                    node: Box::new(node),
+                    quantifier: quantifier.into(),
+                };
+            }
+            nodes.push(node);
+        }
+        if let Some(literal_start) = literal {
+            nodes.push(Node::Literal(self.source[literal_start..self.pos].into()));
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node<'a>, ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        let next = self.peek().expect("not at the end");
+        match next {
+            '(' => self.group(),
+            '[' => self.class(),
+            '\\' if rest.starts_with(r"\%{") => {
+                self.pos += 3;
+                Ok(Node::EscapedPlaceholder)
+            }
+            '\\' => self.escape(),
+            '.' | '^' | '$' => {
+                self.pos += 1;
+                Ok(Node::Meta(rest[..1].into()))
+            }
+            '*' | '+' | '?' => Err(self.error(start, ParseErrorKind::NothingToRepeat)),
+            '%' if rest.starts_with("%{") => self.placeholder(),
+            c => {
+                self.pos += c.len_utf8();
+                Ok(Node::Literal(rest[..c.len_utf8()].into()))
+            }
+        }
+    }
+
+    fn placeholder(&mut self) -> Result<Node<'a>, ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        match grok_split(rest).next() {
+            Some(GrokComponent::GrokPattern {
+                range,
+                name,
+                alias,
+                extract,
+                definition,
+                ..
+            }) => {
+                self.pos += range.end;
+                let definition = if definition.is_empty() {
+                    None
+                } else {
+                    let offset = start + crate::definition_start(&range, definition);
+                    Some(Parser::new(definition, self.offset + offset).parse()?)
+                };
+                Ok(Node::Placeholder(Placeholder {
+                    name: name.into(),
+                    alias: alias.into(),
+                    extract: extract.into(),
+                    definition,
+                    resolved: None,
+                }))
+            }
+            Some(GrokComponent::PatternError(e)) => {
+                let e = e.offset(self.offset + start);
+                Err(ParseError {
+                    offset: e.range().start,
+                    kind: ParseErrorKind::Placeholder(e),
+                })
+            }
+            _ => unreachable!("the pattern starts with a placeholder"),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Node<'a>, ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        let mut chars = rest[1..].chars();
+        let Some(next) = chars.next() else {
+            return Err(self.error(start, ParseErrorKind::TrailingBackslash));
+        };
+        let after = &rest[1 + next.len_utf8()..];
+        let argument = |open: char, close: char| {
+            after
+                .strip_prefix(open)
+                .and_then(|s| s.find(close))
+                .map_or(0, |end| end + 2)
+        };
+        let len = 1
+            + next.len_utf8()
+            + match next {
+                'x' if after.starts_with('{') => argument('{', '}'),
+                'x' => after
+                    .chars()
+                    .take(2)
+                    .take_while(char::is_ascii_hexdigit)
+                    .count(),
+                'p' | 'P' | 'o' | 'N' | 'u' => argument('{', '}'),
+                'k' | 'g' => [argument('<', '>'), argument('\'', '\''), argument('{', '}')]
+                    .into_iter()
+                    .max()
+                    .unwrap_or(0),
+                'c' => after.chars().next().map_or(0, char::len_utf8),
+                '0'..='9' => after.chars().take_while(char::is_ascii_digit).count(),
+                _ => 0,
+            };
+        self.pos += len;
+        let text = &rest[..len];
+        if next.is_alphanumeric() {
+            Ok(Node::Escape(text.into()))
+        } else {
+            Ok(Node::Literal(text.into()))
+        }
+    }
+
+    fn class(&mut self) -> Result<Node<'a>, ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        let mut chars = rest.char_indices().skip(1).peekable();
+        _ = chars.next_if(|(_, c)| *c == '^');
+        // A `]` at the start of a class is literal
+        _ = chars.next_if(|(_, c)| *c == ']');
+        let mut depth = 1;
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\\' => _ = chars.next(),
+                '[' if rest[index..].starts_with("[:") => {
+                    if let Some(end) = rest[index..].find(":]") {
+                        while chars.next_if(|(i, _)| *i < index + end + 2).is_some() {}
+                    }
+                }
+                '[' => {
+                    depth += 1;
+                    _ = chars.next_if(|(_, c)| *c == '^');
+                }
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += index + 1;
+                        return Ok(Node::Class(rest[..index + 1].into()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(self.error(start, ParseErrorKind::UnclosedClass))
+    }
+
+    fn group(&mut self) -> Result<Node<'a>, ParseError> {
+        let start = self.pos;
+        let rest = self.rest();
+        let named = |prefix: &str, close: char| {
+            let name = rest.strip_prefix(prefix)?;
+            let end = name.find(close)?;
+            Some((&name[..end], prefix.len() + end + 1))
+        };
+
+        let (kind, open_len) = if let Some(comment) = rest.strip_prefix("(?#") {
+            let Some(end) = comment.find(')') else {
+                return Err(self.error(start, ParseErrorKind::UnclosedGroup));
+            };
+            (GroupKind::Comment, 3 + end)
+        } else if rest.starts_with("(?:") {
+            (GroupKind::NonCapturing, 3)
+        } else if rest.starts_with("(?=") {
+            (GroupKind::LookAhead, 3)
+        } else if rest.starts_with("(?!") {
+            (GroupKind::NegativeLookAhead, 3)
+        } else if rest.starts_with("(?<=") {
+            (GroupKind::LookBehind, 4)
+        } else if rest.starts_with("(?<!") {
+            (GroupKind::NegativeLookBehind, 4)
+        } else if rest.starts_with("(?>") {
+            (GroupKind::Atomic, 3)
+        } else if let Some((name, len)) = named("(?<", '>')
+            .or_else(|| named("(?P<", '>'))
+            .or_else(|| named("(?'", '\''))
+        {
+            (GroupKind::Named(name.into()), len)
+        } else if let Some(flags) = rest.strip_prefix("(?") {
+            let len = flags
+                .find(|c: char| !c.is_ascii_alphabetic() && c != '-' && c != '^')
+                .unwrap_or(flags.len());
+            match flags[len..].chars().next() {
+                Some(':') => (GroupKind::Flags(flags[..len].into()), 2 + len + 1),
+                // The flags apply to the rest of the enclosing group
+                Some(')') => (GroupKind::Flags(flags[..len].into()), 2 + len),
+                _ => return Err(self.error(start, ParseErrorKind::UnknownGroup)),
+            }
+        } else {
+            (GroupKind::Capturing, 1)
+        };
+
+        let open = &rest[..open_len];
+        self.pos += open_len;
+        let nodes = if matches!(kind, GroupKind::Comment) || rest[open_len..].starts_with(')') {
+            vec![]
+        } else {
+            self.alternation(1)?
+        };
+        if self.peek() != Some(')') {
+            return Err(self.error(start, ParseErrorKind::UnclosedGroup));
+        }
+        self.pos += 1;
+        Ok(Node::Group(Group {
+            kind,
+            open: open.into(),
+            nodes,
+        }))
+    }
+
+    /// Returns the length of the quantifier at the current position, if any.
+    fn quantifier_len(&self) -> Option<usize> {
+        let rest = self.rest();
+        let len = match rest.chars().next()? {
+            '*' | '+' | '?' => 1,
+            '{' => {
+                let end = rest.find('}')?;
+                let interval = &rest[1..end];
+                let (min, max) = interval.split_once(',').unwrap_or((interval, ""));
+                let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+                if (min.is_empty() && max.is_empty()) || !digits(min) || !digits(max) {
+                    return None;
+                }
+                end + 1
+            }
+            _ => return None,
+        };
+        // A lazy or possessive suffix
+        match rest[len..].chars().next() {
+            Some('?' | '+') => Some(len + 1),
+            _ => Some(len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern_parser::ExpectedToken;
+
+    fn literal(s: &str) -> Node<'_> {
+        Node::Literal(s.into())
+    }
+
+    fn placeholder<'a>(name: &'a str, alias: &'a str) -> Node<'a> {
+        Node::Placeholder(Placeholder {
+            name: name.into(),
+            alias: alias.into(),
+            extract: "".into(),
+            definition: None,
+            resolved: None,
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        let ast = parse(r"a\.b+%{WORD:w}(?:x|y|)[a-z\]]{2,}?(?i)\d").unwrap();
+        assert_eq!(
+            vec![
+                literal(r"a\."),
+                Node::Repeat {
+                    node: Box::new(literal("b")),
+                    quantifier: "+".into(),
+                },
+                placeholder("WORD", "w"),
+                Node::Group(Group {
+                    kind: GroupKind::NonCapturing,
+                    open: "(?:".into(),
+                    nodes: vec![Node::Alternation(vec![
+                        vec![literal("x")],
+                        vec![literal("y")],
+                        vec![],
+                    ])],
+                }),
+                Node::Repeat {
+                    node: Box::new(Node::Class(r"[a-z\]]".into())),
+                    quantifier: "{2,}?".into(),
+                },
+                Node::Group(Group {
+                    kind: GroupKind::Flags("i".into()),
+                    open: "(?i".into(),
+                    nodes: vec![],
+                }),
+                Node::Escape(r"\d".into()),
+            ],
+            ast.nodes
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        for (pattern, kind) in [
+            ("(a)", GroupKind::Capturing),
+            ("(?<n>a)", GroupKind::Named("n".into())),
+            ("(?P<n>a)", GroupKind::Named("n".into())),
+            ("(?'n'a)", GroupKind::Named("n".into())),
+            ("(?=a)", GroupKind::LookAhead),
+            ("(?!a)", GroupKind::NegativeLookAhead),
+            ("(?<=a)", GroupKind::LookBehind),
+            ("(?<!a)", GroupKind::NegativeLookBehind),
+            ("(?>a)", GroupKind::Atomic),
+            ("(?i-m:a)", GroupKind::Flags("i-m".into())),
+            ("(?# a (comment)", GroupKind::Comment),
+        ] {
+            let ast = parse(pattern).unwrap();
+            let [Node::Group(group)] = ast.nodes.as_slice() else {
+                panic!("{pattern} should be a group: {ast:?}");
+            };
+            assert_eq!(kind, group.kind, "{pattern}");
+            assert_eq!(pattern, ast.to_string());
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let ast = parse(r"%{A=%{B:b}{2}\}}\%{%{C::int}%%{ns::D:d:float}").unwrap();
+        let Node::Placeholder(a) = &ast.nodes[0] else {
+            panic!("{ast:?}");
+        };
+        let definition = a.definition.as_ref().unwrap();
+        assert!(
+            matches!(&definition.nodes[0], Node::Repeat { node, quantifier }
+            if quantifier == "{2}" && **node == placeholder("B", "b"))
+        );
+        assert_eq!(literal(r"\}"), definition.nodes[1]);
+        assert_eq!(Node::EscapedPlaceholder, ast.nodes[1]);
+        // Like in Logstash, `%%{` is a literal `%` followed by a placeholder
+        assert_eq!(literal("%"), ast.nodes[3]);
+        let Node::Placeholder(d) = &ast.nodes[4] else {
+            panic!("{ast:?}");
+        };
+        assert_eq!(("ns::D", "d", "float"), (&*d.name, &*d.alias, &*d.extract));
+        assert_eq!(
+            r"%{A=%{B:b}{2}\}}\%{%{C::int}%%{ns::D:d:float}",
+            ast.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (pattern, offset, kind) in [
+            ("a(b", 1, ParseErrorKind::UnclosedGroup),
+            ("a)b", 1, ParseErrorKind::UnmatchedParenthesis),
+            ("(a))", 3, ParseErrorKind::UnmatchedParenthesis),
+            ("a(?%b)", 1, ParseErrorKind::UnknownGroup),
+            ("a[bc", 1, ParseErrorKind::UnclosedClass),
+            ("a|*", 2, ParseErrorKind::NothingToRepeat),
+            ("ab\\", 2, ParseErrorKind::TrailingBackslash),
+            (
+                "a%{B!}",
+                4,
+                ParseErrorKind::Placeholder(GrokPatternError::InvalidCharacter {
+                    character: '!',
+                    range: 4..5,
+                    expected: ExpectedToken::OneOf(&[':', '=', '}']),
+                }),
+            ),
+            ("%{A=x(}", 5, ParseErrorKind::UnclosedGroup),
+        ] {
+            assert_eq!(
+                Err(ParseError { offset, kind }),
+                parse(pattern).map(|_| ()),
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_nested_parse_errors() {
+        let error = parse("x%{A=(%{B:})}").unwrap_err();
+        assert_eq!(10, error.offset);
+        assert_eq!(
+            "invalid pattern definition (expected an alias) at offset 10",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_builtin_patterns_round_trip() {
+        for (name, pattern) in crate::patterns()
+            .iter()
+            .chain(crate::linear_patterns())
+            .chain(crate::PatternSet::EcsV1.patterns())
+        {
+            let ast = parse(pattern).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(*pattern, ast.to_string(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_visit_and_rewrite() {
+        struct Count(usize);
+        impl<'a> Visitor<'a> for Count {
+            fn visit(&mut self, node: &Node<'a>) {
+                self.0 += 1;
+                visit_children(self, node);
+            }
+        }
+
+        let ast = parse("a(b|%{C=c*})").unwrap();
+        let mut count = Count(0);
+        ast.visit(&mut count);
+        // a, the group, the alternation, b, C, the repeat and c
+        assert_eq!(7, count.0);
+
+        struct Uppercase;
+        impl<'a> Rewriter<'a> for Uppercase {
+            fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+                match rewrite_children(self, node) {
+                    Node::Literal(s) => Node::Literal(s.to_uppercase().into()),
+                    node => node,
+                }
+            }
+        }
+        assert_eq!("A(B|%{C=C*})", ast.rewrite(&mut Uppercase).to_string());
+    }
+}
@@ -0,0 +1,154 @@
+//! A least-recently-used cache of compiled patterns, see
+//! [`Grok::set_cache_capacity`](crate::Grok::set_cache_capacity).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::Pattern;
+
+/// The key of a cached pattern: the pattern and `with_alias_only`.
+type Key = (String, bool);
+
+#[derive(Clone)]
+struct Entry {
+    pattern: Arc<Pattern>,
+    dependencies: BTreeSet<String>,
+    last_used: u64,
+}
+
+#[derive(Clone)]
+struct Entries {
+    capacity: usize,
+    clock: u64,
+    entries: BTreeMap<Key, Entry>,
+}
+
+pub(crate) struct PatternCache {
+    inner: Mutex<Entries>,
+}
+
+impl PatternCache {
+    pub(crate) const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Entries {
+                capacity: 0,
+                clock: 0,
+                entries: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        // The entries are always consistent, even if a thread panicked.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        let inner = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        inner.capacity = capacity;
+        while inner.entries.len() > capacity {
+            inner.evict();
+        }
+    }
+
+    pub(crate) fn get(&self, pattern: &str, with_alias_only: bool) -> Option<Arc<Pattern>> {
+        let mut inner = self.lock();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner
+            .entries
+            .get_mut(&(pattern.to_string(), with_alias_only))?;
+        entry.last_used = clock;
+        Some(entry.pattern.clone())
+    }
+
+    pub(crate) fn insert(
+        &self,
+        pattern: &str,
+        with_alias_only: bool,
+        compiled: Arc<Pattern>,
+        dependencies: BTreeSet<String>,
+    ) {
+        let mut inner = self.lock();
+        if inner.capacity == 0 {
+            return;
+        }
+        let key = (pattern.to_string(), with_alias_only);
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= inner.capacity {
+            inner.evict();
+        }
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.entries.insert(
+            key,
+            Entry {
+                pattern: compiled,
+                dependencies,
+                last_used,
+            },
+        );
+    }
+
+    /// Drops the patterns that depend on a pattern named `name` in any
+    /// namespace.
+    pub(crate) fn invalidate(&mut self, name: &str) {
+        let inner = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        inner.entries.retain(|_, entry| {
+            !entry
+                .dependencies
+                .iter()
+                .any(|dependency| unqualified(dependency) == name)
+        });
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let inner = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        inner.entries.clear();
+    }
+}
+
+impl Entries {
+    /// Removes the least recently used entry.
+    fn evict(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Strips the namespace from a pattern name.
+fn unqualified(name: &str) -> &str {
+    name.rsplit_once("::").map_or(name, |(_, name)| name)
+}
+
+impl Clone for PatternCache {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.lock().clone()),
+        }
+    }
+}
+
+impl fmt::Debug for PatternCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.lock();
+        f.debug_struct("PatternCache")
+            .field("capacity", &inner.capacity)
+            .field("len", &inner.entries.len())
+            .finish()
+    }
+}
@@ -0,0 +1,368 @@
+use crate::Matches;
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// Errors that can occur when deserializing `Matches` into a type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DeserializeError {
+    /// A captured value could not be parsed into the requested type.
+    InvalidValue {
+        /// The name (or alias) of the capture.
+        name: String,
+        /// The captured text.
+        value: String,
+        /// The type the captured text was expected to parse as.
+        expected: &'static str,
+    },
+    /// A custom error raised by the `Deserialize` implementation.
+    Custom(String),
+}
+
+impl StdError for DeserializeError {}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeserializeError::InvalidValue {
+                ref name,
+                ref value,
+                expected,
+            } => write!(
+                f,
+                "The captured value {:?} for \"{}\" could not be parsed as {}",
+                value, name, expected
+            ),
+            DeserializeError::Custom(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeserializeError::Custom(msg.to_string())
+    }
+}
+
+/// A `Deserializer` that presents the captures of a `Matches` as a map from
+/// name (or alias) to captured text.
+///
+/// Captures that did not participate in the match are absent from the map, so
+/// `Option<T>` fields deserialize as `None` for them.
+pub(crate) struct MatchesDeserializer<'de> {
+    matches: &'de Matches<'de>,
+}
+
+impl<'de> MatchesDeserializer<'de> {
+    pub(crate) fn new(matches: &'de Matches<'de>) -> Self {
+        Self { matches }
+    }
+}
+
+impl<'de> Deserializer<'de> for MatchesDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(MatchesMapAccess {
+            iter: self.matches.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct enum identifier ignored_any
+    }
+}
+
+struct MatchesMapAccess<'de, I> {
+    iter: I,
+    value: Option<(&'de str, &'de str)>,
+}
+
+impl<'de, I: Iterator<Item = (&'de str, &'de str)>> MapAccess<'de> for MatchesMapAccess<'de, I> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((name, value)) => {
+                self.value = Some((name, value));
+                seed.deserialize(BorrowedStrDeserializer::new(name))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let Some((name, value)) = self.value.take() else {
+            return Err(de::Error::custom("value requested before key"));
+        };
+        seed.deserialize(CaptureDeserializer { name, value })
+    }
+}
+
+/// Deserializes a single captured value, parsing it from text where the
+/// requested type is not a string.
+struct CaptureDeserializer<'de> {
+    name: &'de str,
+    value: &'de str,
+}
+
+impl CaptureDeserializer<'_> {
+    fn parse<T: std::str::FromStr>(&self, expected: &'static str) -> Result<T, DeserializeError> {
+        self.value
+            .parse()
+            .map_err(|_| DeserializeError::InvalidValue {
+                name: self.name.to_string(),
+                value: self.value.to_string(),
+                expected,
+            })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CaptureDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+        deserialize_char => visit_char(char),
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // Captures that did not participate never reach this point.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(BorrowedStrDeserializer::new(self.value))
+    }
+
+    forward_to_deserialize_any! {
+        str string seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DeserializeError, Grok};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Verb {
+        Get,
+        Post,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Request {
+        verb: Verb,
+        path: String,
+        status: u16,
+        secure: bool,
+        elapsed: f64,
+        user: Option<String>,
+    }
+
+    fn grok() -> Grok {
+        let mut grok = Grok::empty();
+        grok.add_pattern("WORD", r"\w+");
+        grok.add_pattern("PATH", r"/\S*");
+        grok.add_pattern("INT", r"\d+");
+        grok.add_pattern("NUMBER", r"\d+(?:\.\d+)?");
+        grok.add_pattern("BOOL", r"true|false");
+        grok
+    }
+
+    #[test]
+    fn test_match_into_struct() {
+        let pattern = grok()
+            .compile(
+                r"%{WORD:verb} %{PATH:path} %{INT:status} %{BOOL:secure} %{NUMBER:elapsed}(?: %{WORD:user})?",
+                true,
+            )
+            .unwrap();
+
+        let request: Request = pattern
+            .match_into("get /index.html 200 true 0.25 root")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Request {
+                verb: Verb::Get,
+                path: "/index.html".to_string(),
+                status: 200,
+                secure: true,
+                elapsed: 0.25,
+                user: Some("root".to_string()),
+            },
+            request
+        );
+
+        let request: Request = pattern.match_into("post / 404 false 1").unwrap().unwrap();
+        assert_eq!(Verb::Post, request.verb);
+        assert_eq!(None, request.user);
+
+        assert_eq!(None, pattern.match_into::<Request>("nope").unwrap());
+    }
+
+    #[test]
+    fn test_deserialize_borrowed() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            verb: &'a str,
+            path: &'a str,
+        }
+
+        let pattern = grok().compile(r"%{WORD:verb} %{PATH:path}", true).unwrap();
+        let matches = pattern.match_against("GET /a/b").unwrap();
+        let borrowed: Borrowed = matches.deserialize().unwrap();
+        assert_eq!("GET", borrowed.verb);
+        assert_eq!("/a/b", borrowed.path);
+
+        let map: HashMap<String, String> = matches.deserialize().unwrap();
+        assert_eq!(2, map.len());
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        #[derive(Debug, Deserialize)]
+        #[allow(unused)]
+        struct Status {
+            status: u8,
+        }
+
+        let pattern = grok().compile(r"%{INT:status}", true).unwrap();
+        let err = pattern.match_into::<Status>("1000").unwrap_err();
+        assert_eq!(
+            DeserializeError::InvalidValue {
+                name: "status".to_string(),
+                value: "1000".to_string(),
+                expected: "u8",
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn test_missing_field() {
+        #[derive(Debug, Deserialize)]
+        #[allow(unused)]
+        struct Missing {
+            missing: String,
+        }
+
+        let pattern = grok().compile(r"%{INT:status}", true).unwrap();
+        assert!(matches!(
+            pattern.match_into::<Missing>("1"),
+            Err(DeserializeError::Custom(_))
+        ));
+    }
+}
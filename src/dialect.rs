@@ -0,0 +1,1144 @@
+//! Translation of grok regular expressions between engine dialects.
+//!
+//! Grok patterns (including the built-in ones) are written for Oniguruma's
+//! Ruby syntax. The other engines either reject some of its constructs or
+//! interpret them differently (eg: `\h` is a hex digit in Oniguruma, but
+//! horizontal whitespace in PCRE2, and `(?m)` enables dot-all mode rather than
+//! multi-line mode).
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::ops::Range;
+
+use crate::ast::{self, Group, GroupKind, Node};
+
+/// A regular expression dialect, corresponding to one of the supported engines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// Oniguruma with Ruby syntax, the dialect grok patterns are written in.
+    Onig,
+    /// PCRE2.
+    Pcre2,
+    /// The `fancy-regex` crate.
+    FancyRegex,
+    /// The `regex` crate, which does not support backtracking.
+    Regex,
+}
+
+impl Dialect {
+    /// Returns the dialect of the engine this crate was compiled with.
+    pub fn current() -> Self {
+        crate::ENGINE.into()
+    }
+}
+
+impl From<crate::Engine> for Dialect {
+    fn from(engine: crate::Engine) -> Self {
+        match engine {
+            crate::Engine::Onig => Dialect::Onig,
+            crate::Engine::Pcre2 => Dialect::Pcre2,
+            crate::Engine::FancyRegex => Dialect::FancyRegex,
+            crate::Engine::Regex => Dialect::Regex,
+        }
+    }
+}
+
+/// A regular expression construct that may need special handling when
+/// translating between dialects.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[non_exhaustive]
+pub enum Construct {
+    /// An atomic group: `(?>...)`.
+    AtomicGroup,
+    /// A possessive quantifier: `*+`, `++` or `?+`.
+    PossessiveQuantifier,
+    /// A look-ahead assertion: `(?=...)` or `(?!...)`.
+    LookAhead,
+    /// A look-behind assertion: `(?<=...)` or `(?<!...)`.
+    LookBehind,
+    /// A back-reference: `\1` or `\k<name>`.
+    BackReference,
+    /// A subexpression call: `\g<name>`.
+    SubexpressionCall,
+    /// A conditional group: `(?(cond)yes|no)`.
+    Conditional,
+    /// The absent operator: `(?~...)`.
+    AbsentOperator,
+    /// The search start anchor: `\G`.
+    SearchStartAnchor,
+    /// The end anchor that allows a trailing newline: `\Z`.
+    EndBeforeNewlineAnchor,
+    /// The match start reset: `\K`.
+    KeepOut,
+    /// A negated hex digit escape inside a character class: `[\H]`.
+    NegatedHexDigitInClass,
+    /// A POSIX bracket class: `[[:alpha:]]`.
+    PosixClass,
+}
+
+impl fmt::Display for Construct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Construct::AtomicGroup => "atomic group",
+            Construct::PossessiveQuantifier => "possessive quantifier",
+            Construct::LookAhead => "look-ahead",
+            Construct::LookBehind => "look-behind",
+            Construct::BackReference => "back-reference",
+            Construct::SubexpressionCall => "subexpression call",
+            Construct::Conditional => "conditional group",
+            Construct::AbsentOperator => "absent operator",
+            Construct::SearchStartAnchor => "search start anchor",
+            Construct::EndBeforeNewlineAnchor => "end-of-string-before-newline anchor",
+            Construct::KeepOut => "match start reset",
+            Construct::NegatedHexDigitInClass => "negated hex digit in character class",
+            Construct::PosixClass => "POSIX bracket class",
+        })
+    }
+}
+
+/// A construct found at a given byte range of the source regular expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TranslationIssue {
+    /// The construct.
+    pub construct: Construct,
+    /// The byte range of the construct in the source regular expression.
+    pub range: Range<usize>,
+}
+
+/// A regular expression that was successfully translated to another dialect.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Translation {
+    regex: String,
+    approximations: Vec<TranslationIssue>,
+}
+
+impl Translation {
+    /// Returns the translated regular expression.
+    pub fn regex(&self) -> &str {
+        &self.regex
+    }
+
+    /// Returns the constructs that had no exact equivalent in the target
+    /// dialect and were replaced by an approximation that may match
+    /// differently (eg: a POSIX bracket class, which is Unicode-aware in
+    /// Oniguruma, replaced by its ASCII ranges).
+    pub fn approximations(&self) -> &[TranslationIssue] {
+        &self.approximations
+    }
+
+    /// Consumes this translation, returning the regular expression.
+    pub fn into_regex(self) -> String {
+        self.regex
+    }
+}
+
+/// A regular expression could not be translated to another dialect.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TranslationError {
+    target: Dialect,
+    unsupported: Vec<TranslationIssue>,
+}
+
+impl TranslationError {
+    /// Returns the dialect that was targeted.
+    pub fn target(&self) -> Dialect {
+        self.target
+    }
+
+    /// Returns every construct that could not be translated.
+    pub fn unsupported(&self) -> &[TranslationIssue] {
+        &self.unsupported
+    }
+}
+
+impl StdError for TranslationError {}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unsupported by {:?}:", self.target)?;
+        for (i, issue) in self.unsupported.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(
+                f,
+                "{sep}{} at {}..{}",
+                issue.construct, issue.range.start, issue.range.end
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Translates a regular expression written in the grok (Oniguruma) dialect to
+/// the `target` dialect.
+///
+/// Constructs that behave differently are rewritten to their equivalents
+/// (`\h`, the `m` flag, `{,n}` quantifiers and literal braces). POSIX bracket
+/// classes are replaced by their ASCII ranges, which is reported in
+/// [`Translation::approximations`].
+///
+/// Engines without atomic groups or possessive quantifiers only get a plain
+/// group or quantifier instead when that provably matches the same text: the
+/// group can only match in one way, or the possessive quantifier is followed
+/// by something that none of its repetitions can match. Otherwise, as for
+/// every construct that cannot be translated, they are reported in the
+/// returned error.
+pub fn translate(regex: &str, target: Dialect) -> Result<Translation, TranslationError> {
+    translate_ranges(regex, std::slice::from_ref(&(0..regex.len())), target)
+}
+
+/// Translates the given byte ranges of a regular expression, in ascending
+/// order, copying the text between them unchanged.
+pub(crate) fn translate_ranges(
+    regex: &str,
+    ranges: &[Range<usize>],
+    target: Dialect,
+) -> Result<Translation, TranslationError> {
+    if target == Dialect::Onig || ranges.is_empty() {
+        return Ok(Translation {
+            regex: regex.to_string(),
+            approximations: vec![],
+        });
+    }
+
+    let mut translator = Translator {
+        source: regex,
+        chars: regex.char_indices().collect(),
+        pos: 0,
+        target,
+        output: String::with_capacity(regex.len()),
+        approximations: vec![],
+        unsupported: vec![],
+        relaxable: None,
+    };
+    for range in ranges {
+        translator.copy_to(range.start);
+        while translator.pos < translator.chars.len()
+            && translator.offset(translator.pos) < range.end
+        {
+            translator.step();
+        }
+    }
+    translator.copy_to(regex.len());
+
+    if translator.unsupported.is_empty() {
+        Ok(Translation {
+            regex: translator.output,
+            approximations: translator.approximations,
+        })
+    } else {
+        Err(TranslationError {
+            target,
+            unsupported: translator.unsupported,
+        })
+    }
+}
+
+const HEX_DIGIT: &str = "0-9a-fA-F";
+
+/// Returns the ASCII ranges equivalent to a POSIX bracket class name.
+fn posix_class(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alnum" => "a-zA-Z0-9",
+        "alpha" => "a-zA-Z",
+        "ascii" => r"\x00-\x7F",
+        "blank" => r" \t",
+        "cntrl" => r"\x00-\x1F\x7F",
+        "digit" => "0-9",
+        "graph" => r"!-~",
+        "lower" => "a-z",
+        "print" => r" -~",
+        "punct" => r"!-/:-@\[-`{-~",
+        "space" => r" \t\n\r\x0B\x0C",
+        "upper" => "A-Z",
+        "word" => "a-zA-Z0-9_",
+        "xdigit" => HEX_DIGIT,
+        _ => return None,
+    })
+}
+
+struct Translator<'a> {
+    source: &'a str,
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    target: Dialect,
+    output: String,
+    approximations: Vec<TranslationIssue>,
+    unsupported: Vec<TranslationIssue>,
+    /// The offsets of the atomic groups and possessive quantifiers that can
+    /// be relaxed, found on first use.
+    relaxable: Option<Vec<usize>>,
+}
+
+impl Translator<'_> {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).map(|(_, c)| *c)
+    }
+
+    fn offset(&self, pos: usize) -> usize {
+        self.chars
+            .get(pos)
+            .map_or(self.source.len(), |(index, _)| *index)
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.source[self.offset(self.pos)..].starts_with(s)
+    }
+
+    /// Copies `count` characters to the output unchanged.
+    fn copy(&mut self, count: usize) {
+        let start = self.offset(self.pos);
+        self.pos = (self.pos + count).min(self.chars.len());
+        let end = self.offset(self.pos);
+        self.output.push_str(&self.source[start..end]);
+    }
+
+    /// Copies the source unchanged up to the given byte offset.
+    fn copy_to(&mut self, offset: usize) {
+        let start = self.pos;
+        while self.pos < self.chars.len() && self.offset(self.pos) < offset {
+            self.pos += 1;
+        }
+        let (start, end) = (self.offset(start), self.offset(self.pos));
+        self.output.push_str(&self.source[start..end]);
+    }
+
+    /// Returns whether the atomic group or possessive quantifier at the
+    /// current position matches the same text as its plain equivalent.
+    fn is_relaxable(&mut self) -> bool {
+        let offset = self.offset(self.pos);
+        let source = self.source;
+        self.relaxable
+            .get_or_insert_with(|| relaxable(source))
+            .contains(&offset)
+    }
+
+    /// Records a construct spanning `len` characters from the current
+    /// position, either as unsupported or as approximated.
+    fn record(&mut self, construct: Construct, len: usize, approximated: bool) {
+        let range = self.offset(self.pos)..self.offset(self.pos + len);
+        let issue = TranslationIssue { construct, range };
+        if approximated {
+            self.approximations.push(issue);
+        } else {
+            self.unsupported.push(issue);
+        }
+    }
+
+    fn unsupported_if(&mut self, dialects: &[Dialect], construct: Construct, len: usize) {
+        if dialects.contains(&self.target) {
+            self.record(construct, len, false);
+        }
+    }
+
+    /// Translates the construct at the current position.
+    fn step(&mut self) {
+        let Some(c) = self.peek(0) else {
+            return;
+        };
+        match c {
+            '\\' => self.escape(),
+            '[' => self.class(),
+            '(' => self.group(),
+            '*' | '+' | '?' => self.quantifier(),
+            '{' => self.interval(),
+            '}' => {
+                self.output.push_str(r"\}");
+                self.pos += 1;
+            }
+            _ => self.copy(1),
+        }
+    }
+
+    /// Returns the number of characters in a braced argument (eg: `{...}` or
+    /// `<...>`) starting at `offset`, if one is present.
+    fn braced_len(&self, offset: usize, open: char, close: char) -> Option<usize> {
+        if self.peek(offset) != Some(open) {
+            return None;
+        }
+        let mut len = 1;
+        loop {
+            match self.peek(offset + len) {
+                Some(c) if c == close => return Some(len + 1),
+                Some(_) => len += 1,
+                None => return None,
+            }
+        }
+    }
+
+    fn escape(&mut self) {
+        use Dialect::*;
+
+        let Some(next) = self.peek(1) else {
+            self.copy(1);
+            return;
+        };
+        match next {
+            'h' => {
+                self.output.push('[');
+                self.output.push_str(HEX_DIGIT);
+                self.output.push(']');
+                self.pos += 2;
+            }
+            'H' => {
+                self.output.push_str("[^");
+                self.output.push_str(HEX_DIGIT);
+                self.output.push(']');
+                self.pos += 2;
+            }
+            '1'..='9' => {
+                let mut len = 2;
+                while matches!(self.peek(len), Some('0'..='9')) {
+                    len += 1;
+                }
+                self.unsupported_if(&[Regex], Construct::BackReference, len);
+                self.copy(len);
+            }
+            'k' | 'g' => {
+                let len = 2 + self
+                    .braced_len(2, '<', '>')
+                    .or_else(|| self.braced_len(2, '\'', '\''))
+                    .unwrap_or(0);
+                if next == 'k' {
+                    self.unsupported_if(&[Regex], Construct::BackReference, len);
+                } else {
+                    self.unsupported_if(&[Regex, FancyRegex], Construct::SubexpressionCall, len);
+                }
+                self.copy(len);
+            }
+            'G' => {
+                self.unsupported_if(&[Regex], Construct::SearchStartAnchor, 2);
+                self.copy(2);
+            }
+            'K' => {
+                self.unsupported_if(&[Regex], Construct::KeepOut, 2);
+                self.copy(2);
+            }
+            'Z' => {
+                self.unsupported_if(&[Regex, FancyRegex], Construct::EndBeforeNewlineAnchor, 2);
+                self.copy(2);
+            }
+            'p' | 'P' | 'x' | 'o' => {
+                let len = 2 + self.braced_len(2, '{', '}').unwrap_or(0);
+                self.copy(len);
+            }
+            _ => self.copy(2),
+        }
+    }
+
+    /// Translates a bracketed character class. Nested classes that are not
+    /// negated are flattened into their parent, as not every engine supports
+    /// nesting.
+    fn class(&mut self) {
+        // Whether each open class emitted its brackets.
+        let mut stack: Vec<bool> = vec![];
+        while let Some(c) = self.peek(0) {
+            match c {
+                '[' if !stack.is_empty() && self.peek(1) == Some(':') => {
+                    let Some(len) = self.braced_len(0, '[', ']') else {
+                        self.copy(1);
+                        continue;
+                    };
+                    let start = self.offset(self.pos + 2);
+                    let end = self.offset(self.pos + len - 2);
+                    let name = &self.source[start..end];
+                    let is_posix = self.peek(len - 2) == Some(':');
+                    match posix_class(name).filter(|_| is_posix) {
+                        Some(ranges) => {
+                            self.record(Construct::PosixClass, len, true);
+                            self.output.push_str(ranges);
+                            self.pos += len;
+                        }
+                        _ => self.copy(len),
+                    }
+                }
+                '[' => {
+                    let negated = self.peek(1) == Some('^');
+                    if stack.is_empty() || negated {
+                        stack.push(true);
+                        self.copy(if negated { 2 } else { 1 });
+                    } else {
+                        stack.push(false);
+                        self.pos += 1;
+                    }
+                    // A leading `]` is a literal
+                    if self.peek(0) == Some(']') {
+                        self.copy(1);
+                    }
+                }
+                ']' => {
+                    if stack.pop() == Some(true) {
+                        self.copy(1);
+                    } else {
+                        self.pos += 1;
+                    }
+                    if stack.is_empty() {
+                        return;
+                    }
+                }
+                '\\' => match self.peek(1) {
+                    Some('h') => {
+                        self.output.push_str(HEX_DIGIT);
+                        self.pos += 2;
+                    }
+                    Some('H') => {
+                        self.record(Construct::NegatedHexDigitInClass, 2, false);
+                        self.copy(2);
+                    }
+                    Some('p' | 'P' | 'x' | 'o') => {
+                        let len = 2 + self.braced_len(2, '{', '}').unwrap_or(0);
+                        self.copy(len);
+                    }
+                    _ => self.copy(2),
+                },
+                _ => self.copy(1),
+            }
+        }
+    }
+
+    fn group(&mut self) {
+        use Dialect::*;
+
+        if self.peek(1) != Some('?') {
+            self.copy(1);
+            return;
+        }
+
+        if self.starts_with("(?=") || self.starts_with("(?!") {
+            self.unsupported_if(&[Regex], Construct::LookAhead, 3);
+            self.copy(3);
+        } else if self.starts_with("(?<=") || self.starts_with("(?<!") {
+            self.unsupported_if(&[Regex], Construct::LookBehind, 4);
+            self.copy(4);
+        } else if self.starts_with("(?>") {
+            if self.target == Regex && self.is_relaxable() {
+                self.output.push_str("(?:");
+                self.pos += 3;
+            } else {
+                self.unsupported_if(&[Regex], Construct::AtomicGroup, 3);
+                self.copy(3);
+            }
+        } else if self.starts_with("(?~") {
+            self.record(Construct::AbsentOperator, 3, false);
+            self.copy(3);
+        } else if self.starts_with("(?(") {
+            self.unsupported_if(&[Regex, FancyRegex], Construct::Conditional, 3);
+            self.copy(3);
+        } else if self.starts_with("(?#") {
+            let len = self.braced_len(1, '?', ')').map_or(2, |len| len + 1);
+            if self.target == Regex {
+                self.pos += len;
+            } else {
+                self.copy(len);
+            }
+        } else if self.starts_with("(?'") {
+            let Some(len) = self.braced_len(2, '\'', '\'') else {
+                self.copy(3);
+                return;
+            };
+            let start = self.offset(self.pos + 3);
+            let end = self.offset(self.pos + len + 1);
+            self.output.push_str("(?<");
+            self.output.push_str(&self.source[start..end]);
+            self.output.push('>');
+            self.pos += len + 2;
+        } else if self.starts_with("(?<") || self.starts_with("(?P<") || self.starts_with("(?:") {
+            self.copy(3);
+        } else {
+            // Option flags: `(?imx-imx)` or `(?imx-imx:...)`. Ruby's `m` is
+            // dot-all, which is `s` everywhere else.
+            self.copy(2);
+            while let Some(c) = self.peek(0) {
+                match c {
+                    'm' => {
+                        self.output.push('s');
+                        self.pos += 1;
+                    }
+                    'a'..='z' | '-' => self.copy(1),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    fn quantifier(&mut self) {
+        self.copy(1);
+        self.possessive();
+    }
+
+    /// Relaxes a possessive `+` suffix, if any, after a quantifier.
+    fn possessive(&mut self) {
+        if self.peek(0) == Some('+') {
+            if self.target == Dialect::Regex && self.is_relaxable() {
+                self.pos += 1;
+            } else {
+                self.unsupported_if(&[Dialect::Regex], Construct::PossessiveQuantifier, 1);
+                self.copy(1);
+            }
+        }
+    }
+
+    /// Translates an interval quantifier, or escapes the brace if it does not
+    /// start one.
+    fn interval(&mut self) {
+        let Some(len) = self.braced_len(0, '{', '}') else {
+            self.output.push_str(r"\{");
+            self.pos += 1;
+            return;
+        };
+        let start = self.offset(self.pos + 1);
+        let end = self.offset(self.pos + len - 1);
+        let body = &self.source[start..end];
+        let (min, max) = body.split_once(',').unwrap_or((body, ""));
+        let is_number = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if !is_number(min) || !is_number(max) || body.is_empty() || body == "," {
+            self.output.push_str(r"\{");
+            self.pos += 1;
+            return;
+        }
+
+        if min.is_empty() {
+            self.output.push_str("{0");
+            self.output.push_str(&body[min.len()..]);
+            self.output.push('}');
+            self.pos += len;
+        } else {
+            self.copy(len);
+        }
+    }
+}
+
+/// Returns the offsets of the atomic groups, and of the possessive suffixes of
+/// quantifiers, that match the same text as their plain equivalents, so that
+/// engines without backtracking control can use those instead.
+///
+/// An atomic group can be relaxed if its contents can only match in one way,
+/// as backtracking into it then never finds another match. A possessive
+/// quantifier can be relaxed if it repeats a single character that cannot
+/// start what follows it, as giving back a repetition can then never let the
+/// rest of the regex match. The analysis is conservative: anything it cannot
+/// reason about, such as placeholders or Unicode classes, is not relaxed.
+fn relaxable(source: &str) -> Vec<usize> {
+    let mut offsets = vec![];
+    if let Ok(ast) = ast::parse(source) {
+        find_relaxable(&ast.nodes, 0, &mut offsets);
+    }
+    offsets
+}
+
+fn find_relaxable(nodes: &[Node<'_>], mut offset: usize, offsets: &mut Vec<usize>) {
+    for (index, node) in nodes.iter().enumerate() {
+        let len = node.to_string().len();
+        match node {
+            Node::Group(group) => {
+                if group.kind == GroupKind::Atomic && is_unambiguous(&group.nodes) {
+                    offsets.push(offset);
+                }
+                find_relaxable(&group.nodes, offset + group.open.len(), offsets);
+            }
+            Node::Alternation(alternatives) => {
+                let mut start = offset;
+                for alternative in alternatives {
+                    find_relaxable(alternative, start, offsets);
+                    start += alternative
+                        .iter()
+                        .map(|n| n.to_string().len())
+                        .sum::<usize>()
+                        + 1;
+                }
+            }
+            Node::Repeat { node, quantifier } => {
+                find_relaxable(std::slice::from_ref(node), offset, offsets);
+                if is_possessive(quantifier) && gives_nothing_back(node, nodes.get(index + 1)) {
+                    offsets.push(offset + len - 1);
+                }
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+}
+
+fn is_possessive(quantifier: &str) -> bool {
+    matches!(quantifier, "*+" | "++" | "?+")
+}
+
+fn is_exact(quantifier: &str) -> bool {
+    quantifier
+        .strip_prefix('{')
+        .and_then(|q| q.strip_suffix('}'))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Returns whether a repetition of `node` followed by `next` can only match in
+/// one way, because `node` is a single character that cannot start `next`.
+fn gives_nothing_back(node: &Node<'_>, next: Option<&Node<'_>>) -> bool {
+    let (Some(repeated), Some(next)) = (single_char(node), next.and_then(first_char)) else {
+        return false;
+    };
+    repeated.is_disjoint(&next)
+}
+
+/// Returns whether a sequence of nodes can only match in one way.
+fn is_unambiguous(nodes: &[Node<'_>]) -> bool {
+    nodes.iter().enumerate().all(|(index, node)| match node {
+        Node::Literal(_) | Node::Escape(_) | Node::Meta(_) | Node::Class(_) => true,
+        Node::Group(group) => is_unambiguous(&group.nodes),
+        Node::Alternation(alternatives) => {
+            let first = alternatives
+                .iter()
+                .map(|alternative| alternative.first().and_then(first_char))
+                .collect::<Option<Vec<_>>>();
+            let Some(first) = first else {
+                return false;
+            };
+            let disjoint = first
+                .iter()
+                .enumerate()
+                .all(|(i, set)| first[i + 1..].iter().all(|other| set.is_disjoint(other)));
+            disjoint && alternatives.iter().all(|a| is_unambiguous(a))
+        }
+        Node::Repeat { node, quantifier } if is_exact(quantifier) => {
+            is_unambiguous(std::slice::from_ref(node)) && is_fixed(node)
+        }
+        Node::Repeat { node, quantifier } if is_possessive(quantifier) => {
+            single_char(node).is_some()
+        }
+        Node::Repeat { node, .. } => gives_nothing_back(node, nodes.get(index + 1)),
+        Node::Placeholder(_) | Node::EscapedPlaceholder => false,
+    })
+}
+
+/// Returns whether a node always matches the same number of characters.
+fn is_fixed(node: &Node<'_>) -> bool {
+    match node {
+        Node::Literal(_) | Node::Escape(_) | Node::Meta(_) | Node::Class(_) => true,
+        Node::Group(group) => group.nodes.iter().all(is_fixed),
+        Node::Repeat { node, quantifier } => is_exact(quantifier) && is_fixed(node),
+        _ => false,
+    }
+}
+
+/// Returns the characters a node matching exactly one character can match.
+fn single_char(node: &Node<'_>) -> Option<CharSet> {
+    match node {
+        Node::Literal(text) => {
+            let mut chars = text.chars();
+            let c = match chars.next()? {
+                '\\' => chars.next()?,
+                c => c,
+            };
+            chars.next().is_none().then(|| CharSet::literal(c))
+        }
+        Node::Escape(text) => CharSet::escape(text),
+        Node::Class(text) => CharSet::class(text),
+        Node::Meta(meta) if meta == "." => Some(CharSet {
+            negated: true,
+            items: vec![Item::Range('\n', '\n')],
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the characters the first character matched by a node can be, if
+/// the node always matches at least one character.
+fn first_char(node: &Node<'_>) -> Option<CharSet> {
+    match node {
+        Node::Literal(text) => {
+            let mut chars = text.chars();
+            let c = match chars.next()? {
+                '\\' => chars.next()?,
+                c => c,
+            };
+            Some(CharSet::literal(c))
+        }
+        Node::Group(Group {
+            kind: GroupKind::Capturing | GroupKind::Named(_) | GroupKind::NonCapturing,
+            nodes,
+            ..
+        })
+        | Node::Group(Group {
+            kind: GroupKind::Atomic,
+            nodes,
+            ..
+        }) => first_char(nodes.first()?),
+        Node::Repeat { node, quantifier } => {
+            let min = quantifier.trim_start_matches('{');
+            let at_least_once = quantifier.starts_with('+')
+                || (quantifier.starts_with('{')
+                    && min.starts_with(|c: char| c.is_ascii_digit())
+                    && !min.starts_with("0"));
+            at_least_once.then(|| first_char(node)).flatten()
+        }
+        Node::Alternation(alternatives) => {
+            let mut items = vec![];
+            for alternative in alternatives {
+                let set = first_char(alternative.first()?)?;
+                if set.negated {
+                    return None;
+                }
+                items.extend(set.items);
+            }
+            Some(CharSet {
+                negated: false,
+                items,
+            })
+        }
+        node => single_char(node),
+    }
+}
+
+/// A set of characters, as matched by a character class.
+#[derive(Debug)]
+struct CharSet {
+    negated: bool,
+    items: Vec<Item>,
+}
+
+#[derive(Debug)]
+enum Item {
+    Range(char, char),
+    /// `\d`, or `\D` if negated.
+    Digit {
+        negated: bool,
+    },
+    /// `\w`, or `\W` if negated.
+    Word {
+        negated: bool,
+    },
+    /// `\s`, or `\S` if negated.
+    Space {
+        negated: bool,
+    },
+}
+
+impl Item {
+    /// Returns whether the item contains a character, or `None` if that
+    /// depends on the Unicode tables of the engine.
+    fn contains(&self, c: char) -> Option<bool> {
+        let (contains, negated) = match *self {
+            Item::Range(start, end) => return Some(start <= c && c <= end),
+            Item::Digit { negated } if c.is_ascii() => (c.is_ascii_digit(), negated),
+            Item::Digit { negated } if !c.is_numeric() => (false, negated),
+            Item::Word { negated } if c.is_ascii() => {
+                (c.is_ascii_alphanumeric() || c == '_', negated)
+            }
+            Item::Space { negated } => (c.is_whitespace(), negated),
+            _ => return None,
+        };
+        Some(contains != negated)
+    }
+}
+
+impl CharSet {
+    fn literal(c: char) -> Self {
+        CharSet {
+            negated: false,
+            items: vec![Item::Range(c, c)],
+        }
+    }
+
+    /// Parses a class escape, such as `\d`, or an escaped character.
+    fn escape(text: &str) -> Option<Self> {
+        let item = match text {
+            r"\d" | r"\D" => Item::Digit {
+                negated: text == r"\D",
+            },
+            r"\w" | r"\W" => Item::Word {
+                negated: text == r"\W",
+            },
+            r"\s" | r"\S" => Item::Space {
+                negated: text == r"\S",
+            },
+            r"\t" => Item::Range('\t', '\t'),
+            r"\n" => Item::Range('\n', '\n'),
+            r"\r" => Item::Range('\r', '\r'),
+            r"\h" | r"\H" => {
+                return Some(CharSet {
+                    negated: text == r"\H",
+                    items: vec![
+                        Item::Range('0', '9'),
+                        Item::Range('a', 'f'),
+                        Item::Range('A', 'F'),
+                    ],
+                })
+            }
+            _ => return None,
+        };
+        Some(CharSet {
+            negated: false,
+            items: vec![item],
+        })
+    }
+
+    /// Parses a bracketed class without nested classes.
+    fn class(text: &str) -> Option<Self> {
+        let body = text.strip_prefix('[')?.strip_suffix(']')?;
+        let (negated, body) = match body.strip_prefix('^') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        let mut items = vec![];
+        let mut chars = body.chars().peekable();
+        while let Some(c) = chars.next() {
+            let start = match c {
+                '[' | '&' => return None,
+                '\\' => {
+                    let escaped = chars.next()?;
+                    if escaped.is_ascii_alphanumeric() {
+                        let set = Self::escape(&format!("\\{escaped}"))?;
+                        if set.negated {
+                            return None;
+                        }
+                        items.extend(set.items);
+                        continue;
+                    }
+                    escaped
+                }
+                c => c,
+            };
+            let mut lookahead = chars.clone();
+            match (lookahead.next(), lookahead.next()) {
+                (Some('-'), Some(end)) if end != '\\' && end != '[' => {
+                    chars.next();
+                    chars.next();
+                    items.push(Item::Range(start, end));
+                }
+                _ => items.push(Item::Range(start, start)),
+            }
+        }
+        Some(CharSet { negated, items })
+    }
+
+    /// Returns whether the set contains a character, or `None` if unknown.
+    fn contains(&self, c: char) -> Option<bool> {
+        let mut contains = Some(false);
+        for item in &self.items {
+            match item.contains(c) {
+                Some(true) => {
+                    contains = Some(true);
+                    break;
+                }
+                Some(false) => {}
+                None => contains = None,
+            }
+        }
+        contains.map(|contains| contains != self.negated)
+    }
+
+    /// Returns every character of a small set that is not negated.
+    fn chars(&self) -> Option<Vec<char>> {
+        if self.negated {
+            return None;
+        }
+        let mut chars = vec![];
+        for item in &self.items {
+            let Item::Range(start, end) = *item else {
+                return None;
+            };
+            if chars.len() + (end as usize).saturating_sub(start as usize) > 1024 {
+                return None;
+            }
+            chars.extend(start..=end);
+        }
+        Some(chars)
+    }
+
+    /// Returns whether no character, in any case, is in both sets.
+    fn is_disjoint(&self, other: &CharSet) -> bool {
+        let excludes = |set: &CharSet, other: &CharSet| {
+            set.chars().is_some_and(|chars| {
+                chars.iter().all(|&c| {
+                    let mut cases = c.to_lowercase().chain(c.to_uppercase());
+                    other.contains(c) == Some(false)
+                        && cases.all(|c| other.contains(c) == Some(false))
+                })
+            })
+        };
+        let complement = match (&self.items[..], &other.items[..]) {
+            ([Item::Digit { negated: a }], [Item::Digit { negated: b }])
+            | ([Item::Word { negated: a }], [Item::Word { negated: b }])
+            | ([Item::Space { negated: a }], [Item::Space { negated: b }]) => {
+                a ^ self.negated != b ^ other.negated
+            }
+            _ => false,
+        };
+        complement || excludes(self, other) || excludes(other, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex(source: &str) -> String {
+        translate(source, Dialect::Regex).unwrap().into_regex()
+    }
+
+    fn unsupported(source: &str, target: Dialect) -> Vec<(Construct, &str)> {
+        translate(source, target)
+            .unwrap_err()
+            .unsupported()
+            .iter()
+            .map(|issue| (issue.construct, &source[issue.range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_onig_is_identity() {
+        let source = r"(?>\h+)(?<!x)[[:alnum:]]{,3}";
+        assert_eq!(source, translate(source, Dialect::Onig).unwrap().regex());
+    }
+
+    #[test]
+    fn test_hex_digit() {
+        assert_eq!("[0-9a-fA-F]{32}", regex(r"\h{32}"));
+        assert_eq!("[^0-9a-fA-F]", regex(r"\H"));
+        assert_eq!("[0-9a-fA-F-]", regex(r"[\h-]"));
+        assert_eq!(
+            vec![(Construct::NegatedHexDigitInClass, r"\H")],
+            unsupported(r"[\H]", Dialect::Pcre2)
+        );
+    }
+
+    #[test]
+    fn test_posix_classes() {
+        assert_eq!("[a-zA-Z0-9_]", regex("[[:alnum:]_]"));
+        assert_eq!(
+            r"(/[a-zA-Z0-9_%!$@:\.,+~-]*)+",
+            regex(r"(/[[[:alnum:]]_%!$@:\.,+~-]*)+")
+        );
+        assert_eq!("[ab[^c]]", regex("[a[b][^c]]"));
+        assert_eq!("[[:^alpha:]]", regex("[[:^alpha:]]"));
+        assert_eq!("[:alpha:]", regex("[:alpha:]"));
+        assert_eq!("[[:bogus:]]", regex("[[:bogus:]]"));
+        assert_eq!(r"[\]:alpha:]", regex(r"[\]:alpha:]"));
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!("(?s)a.b", regex("(?m)a.b"));
+        assert_eq!("(?is:a.b)", regex("(?im:a.b)"));
+        assert_eq!("(?i-s)a", regex("(?i-m)a"));
+    }
+
+    #[test]
+    fn test_braces() {
+        assert_eq!(r"a{2}b{2,}c{0,3}", regex(r"a{2}b{2,}c{,3}"));
+        assert_eq!(r"\{(?<x>.*)\}", regex(r"{(?<x>.*)}"));
+        assert_eq!(r"a\{,\}", regex(r"a{,}"));
+        assert_eq!(r"\p{Greek}\x{41}", regex(r"\p{Greek}\x{41}"));
+    }
+
+    #[test]
+    fn test_approximations() {
+        let translation = translate("[[:alpha:]][^[:digit:]]", Dialect::Pcre2).unwrap();
+        assert_eq!("[a-zA-Z][^0-9]", translation.regex());
+        assert_eq!(
+            vec![
+                TranslationIssue {
+                    construct: Construct::PosixClass,
+                    range: 1..10
+                },
+                TranslationIssue {
+                    construct: Construct::PosixClass,
+                    range: 13..22
+                },
+            ],
+            translation.approximations()
+        );
+
+        let translation = translate(r"(?>\d\d){1,2}a", Dialect::Pcre2).unwrap();
+        assert_eq!(r"(?>\d\d){1,2}a", translation.regex());
+        assert!(translation.approximations().is_empty());
+    }
+
+    #[test]
+    fn test_relaxed_backtracking_control() {
+        // The contents can only match in one way
+        assert_eq!(r"(?:\d\d){1,2}a", regex(r"(?>\d\d){1,2}a"));
+        assert_eq!(r"(?:ab|c\d{2})", regex(r"(?>ab|c\d{2})"));
+        assert_eq!(r"(?:[0-9]+\.)", regex(r"(?>[0-9]+\.)"));
+        // Nothing following the repetition can start with what it repeats
+        assert_eq!(r"\d+(?: |, )", regex(r"\d++(?: |, )"));
+        assert_eq!(r"[^,]*,", regex(r"[^,]*+,"));
+        assert_eq!(r"\s+\S", regex(r"\s++\S"));
+
+        // Backtracking into these finds other matches
+        let unsupported = |source| super::tests::unsupported(source, Dialect::Regex);
+        assert_eq!(
+            vec![(Construct::AtomicGroup, "(?>")],
+            unsupported(r"(?>a|ab)c")
+        );
+        assert_eq!(
+            vec![(Construct::AtomicGroup, "(?>")],
+            unsupported(r"(?>\d+)")
+        );
+        assert_eq!(
+            vec![(Construct::AtomicGroup, "(?>")],
+            unsupported(r"(?>\w+\d)")
+        );
+        assert_eq!(
+            vec![(Construct::AtomicGroup, "(?>")],
+            unsupported(r"(?>%{X})")
+        );
+        assert_eq!(
+            vec![(Construct::PossessiveQuantifier, "+")],
+            unsupported(r"a++")
+        );
+        assert_eq!(
+            vec![(Construct::PossessiveQuantifier, "+")],
+            unsupported(r"\w++\d")
+        );
+        assert_eq!(
+            vec![(Construct::PossessiveQuantifier, "+")],
+            unsupported(r"a*+A")
+        );
+        assert_eq!(
+            vec![(Construct::PossessiveQuantifier, "+")],
+            unsupported(r"\w++é")
+        );
+        assert_eq!(
+            vec![(Construct::PossessiveQuantifier, "+")],
+            unsupported(r"a++b?")
+        );
+    }
+
+    #[test]
+    fn test_named_groups_and_comments() {
+        assert_eq!(
+            "(?<x>a)(?<y>b)(?P<z>c)d",
+            regex("(?<x>a)(?'y'b)(?P<z>c)(?#comment)d")
+        );
+        assert_eq!(
+            "a(?#comment)b",
+            translate("a(?#comment)b", Dialect::Pcre2).unwrap().regex()
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let source = r"(?<![0-9])a(?=b)(x)\1\k<x>\G\Z\K";
+        assert_eq!(
+            vec![
+                (Construct::LookBehind, "(?<!"),
+                (Construct::LookAhead, "(?="),
+                (Construct::BackReference, r"\1"),
+                (Construct::BackReference, r"\k<x>"),
+                (Construct::SearchStartAnchor, r"\G"),
+                (Construct::EndBeforeNewlineAnchor, r"\Z"),
+                (Construct::KeepOut, r"\K"),
+            ],
+            unsupported(source, Dialect::Regex)
+        );
+        assert_eq!(
+            vec![(Construct::EndBeforeNewlineAnchor, r"\Z")],
+            unsupported(source, Dialect::FancyRegex)
+        );
+        assert!(translate(source, Dialect::Pcre2).is_ok());
+
+        let err = translate("(?=a)", Dialect::Regex).unwrap_err();
+        assert_eq!(Dialect::Regex, err.target());
+        assert_eq!("Unsupported by Regex: look-ahead at 0..3", err.to_string());
+    }
+}
@@ -0,0 +1,177 @@
+//! User-provided regex engines, see [`GrokEngine`].
+
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+use crate::dialect::Dialect;
+use crate::Error;
+
+/// A regex engine that patterns can be compiled with instead of the engine
+/// selected by the crate features, set with
+/// [`Grok::set_engine`](crate::Grok::set_engine).
+///
+/// Grok patterns are expanded into a single regular expression, the parts of
+/// which that come from built-in definitions are translated to the
+/// [`Dialect`] of the engine, and then compiled with
+/// [`GrokEngine::compile`]. The matches of the compiled regex are looked up
+/// through the names and spans of its capture groups.
+pub trait GrokEngine: fmt::Debug + Send + Sync {
+    /// Returns the dialect the built-in definitions are translated to before
+    /// patterns are compiled.
+    fn dialect(&self) -> Dialect;
+
+    /// Compiles a regular expression. Failures are expected to be reported
+    /// as [`Error::RegexCompilationFailed`].
+    fn compile(&self, regex: &str) -> Result<Box<dyn CompiledRegex>, Error>;
+}
+
+/// A regular expression compiled by a [`GrokEngine`].
+pub trait CompiledRegex: fmt::Debug + Send + Sync {
+    /// Returns the name and index of every named capture group. Index 0 is
+    /// the whole match.
+    fn capture_names(&self) -> Vec<(String, usize)>;
+
+    /// Returns whether the regex matches somewhere in the text.
+    fn is_match(&self, text: &str) -> bool {
+        self.captures(text).is_some()
+    }
+
+    /// Returns the span of the first match in the text.
+    fn find(&self, text: &str) -> Option<Range<usize>> {
+        self.captures(text)?.into_iter().next().flatten()
+    }
+
+    /// Returns the spans of the capture groups of the first match in the text,
+    /// indexed like [`CompiledRegex::capture_names`], or `None` if the regex
+    /// does not match. Groups that did not take part in the match have no
+    /// span.
+    fn captures(&self, text: &str) -> Option<Vec<Option<Range<usize>>>>;
+}
+
+/// The `Pattern` represents a compiled regex, ready to be matched against arbitrary text.
+#[derive(Debug)]
+pub(crate) struct CustomPattern {
+    pub regex: Box<dyn CompiledRegex>,
+    pub names: BTreeMap<String, Vec<usize>>,
+}
+
+impl CustomPattern {
+    /// Creates a new pattern from a raw regex string and an alias map to identify the
+    /// fields properly.
+    pub(crate) fn new(
+        engine: &dyn GrokEngine,
+        regex: &str,
+        alias: &HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let regex = engine.compile(regex)?;
+        let groups = regex.capture_names();
+        let names = crate::group_names(
+            groups.iter().map(|(name, idx)| (*idx, name.as_str())),
+            alias,
+        );
+        Ok(Self { regex, names })
+    }
+
+    /// Matches this compiled `Pattern` against the text and returns the matches.
+    pub fn match_against<'a>(&'a self, text: &'a str) -> Option<CustomMatches<'a>> {
+        self.regex.captures(text).map(|spans| CustomMatches {
+            text,
+            spans,
+            pattern: self,
+        })
+    }
+
+    /// Returns whether the pattern matches somewhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Returns the span of the first match in the text.
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        self.regex.find(text)
+    }
+
+    /// Returns all names this `Pattern` captures.
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        self.names.keys().map(|s| s.as_str())
+    }
+}
+
+/// The `Matches` represent matched results from a `Pattern` against a provided text.
+#[derive(Debug)]
+pub(crate) struct CustomMatches<'a> {
+    text: &'a str,
+    spans: Vec<Option<Range<usize>>>,
+    pub pattern: &'a CustomPattern,
+}
+
+impl<'a> CustomMatches<'a> {
+    /// Gets the value for the name (or) alias if found, `None` otherwise.
+    pub fn get(&self, name_or_alias: &str) -> Option<&str> {
+        self.pattern
+            .names
+            .get(name_or_alias)?
+            .iter()
+            .rev()
+            .find_map(|&idx| self.span(idx))
+            .map(|span| &self.text[span])
+    }
+
+    /// Gets the values of every group with the name (or) alias that took part
+    /// in the match, along with their index.
+    pub fn get_all(&self, name_or_alias: &str) -> Vec<(usize, &str)> {
+        let indices = self.pattern.names.get(name_or_alias).into_iter().flatten();
+        indices
+            .filter_map(|&idx| Some((idx, &self.text[self.span(idx)?])))
+            .collect()
+    }
+
+    /// Returns the span of the group with the given index, if it took part in
+    /// the match.
+    fn span(&self, idx: usize) -> Option<Range<usize>> {
+        self.spans.get(idx)?.clone()
+    }
+
+    /// Returns a tuple of key/value with all the matches found.
+    pub fn iter(&'a self) -> CustomMatchesIter<'a> {
+        CustomMatchesIter {
+            text: self.text,
+            spans: &self.spans,
+            names: self.pattern.names.iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a CustomMatches<'a> {
+    type Item = (&'a str, &'a str);
+    type IntoIter = CustomMatchesIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An `Iterator` over all matches, accessible via `Matches`.
+pub(crate) struct CustomMatchesIter<'a> {
+    text: &'a str,
+    spans: &'a [Option<Range<usize>>],
+    names: btree_map::Iter<'a, String, Vec<usize>>,
+}
+
+impl<'a> Iterator for CustomMatchesIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (k, v) in self.names.by_ref() {
+            let span = v
+                .iter()
+                .rev()
+                .find_map(|&idx| self.spans.get(idx)?.as_ref());
+            if let Some(span) = span {
+                return Some((k.as_str(), &self.text[span.clone()]));
+            }
+        }
+        None
+    }
+}
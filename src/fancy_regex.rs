@@ -1,6 +1,7 @@
 use crate::Error;
 use fancy_regex::{Captures, Regex};
 use std::collections::{btree_map, BTreeMap, HashMap};
+use std::ops::Range;
 
 pub(crate) const ENGINE: crate::Engine = crate::Engine::FancyRegex;
 
@@ -8,7 +9,7 @@ pub(crate) const ENGINE: crate::Engine = crate::Engine::FancyRegex;
 #[derive(Debug)]
 pub(crate) struct FancyRegexPattern {
     pub regex: Regex,
-    pub names: BTreeMap<String, usize>,
+    pub names: BTreeMap<String, Vec<usize>>,
 }
 
 impl FancyRegexPattern {
@@ -17,13 +18,9 @@ impl FancyRegexPattern {
     pub(crate) fn new(regex: &str, alias: &HashMap<String, String>) -> Result<Self, Error> {
         match Regex::new(regex) {
             Ok(r) => Ok({
-                let mut names = BTreeMap::new();
-                for (i, name) in r.capture_names().enumerate() {
-                    if let Some(name) = name {
-                        let name = alias.get(name).map_or(name, |s| s).to_string();
-                        names.insert(name, i);
-                    }
-                }
+                let groups = r.capture_names().enumerate();
+                let names =
+                    crate::group_names(groups.filter_map(|(i, name)| Some((i, name?))), alias);
                 Self { regex: r, names }
             }),
             Err(e) => Err(Error::RegexCompilationFailed(format!(
@@ -34,12 +31,25 @@ impl FancyRegexPattern {
 
     /// Matches this compiled `Pattern` against the text and returns the matches.
     pub fn match_against<'a>(&'a self, text: &'a str) -> Option<FancyRegexMatches<'a>> {
-        self.regex.captures(text).ok().flatten().and_then(|caps| {
-            Some(FancyRegexMatches {
+        self.regex
+            .captures(text)
+            .ok()
+            .flatten()
+            .map(|caps| FancyRegexMatches {
                 captures: caps,
                 pattern: self,
             })
-        })
+    }
+
+    /// Returns whether the pattern matches somewhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text).unwrap_or_default()
+    }
+
+    /// Returns the span of the first match in the text, without resolving the
+    /// capture groups.
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        self.regex.find(text).ok().flatten().map(|m| m.range())
     }
 
     /// Returns all names this `Pattern` captures.
@@ -60,11 +70,23 @@ impl<'a> FancyRegexMatches<'a> {
     pub fn get(&self, name_or_alias: &str) -> Option<&str> {
         self.pattern
             .names
-            .get(name_or_alias)
-            .and_then(|&idx| self.captures.get(idx))
+            .get(name_or_alias)?
+            .iter()
+            .rev()
+            .find_map(|&idx| self.captures.get(idx))
             .map(|m| m.as_str())
     }
 
+    /// Gets the values of every group with the name (or) alias that took part
+    /// in the match, along with their index.
+    pub fn get_all(&self, name_or_alias: &str) -> Vec<(usize, &str)> {
+        let indices = self.pattern.names.get(name_or_alias).into_iter().flatten();
+        indices
+            .filter_map(|&idx| Some((idx, self.captures.get(idx)?)))
+            .map(|(idx, m)| (idx, m.as_str()))
+            .collect()
+    }
+
     /// Returns a tuple of key/value with all the matches found.
     pub fn iter(&'a self) -> FancyRegexMatchesIter<'a> {
         FancyRegexMatchesIter {
@@ -86,15 +108,15 @@ impl<'a> IntoIterator for &'a FancyRegexMatches<'a> {
 /// An `Iterator` over all matches, accessible via `Matches`.
 pub(crate) struct FancyRegexMatchesIter<'a> {
     captures: &'a Captures<'a>,
-    names: btree_map::Iter<'a, String, usize>,
+    names: btree_map::Iter<'a, String, Vec<usize>>,
 }
 
 impl<'a> Iterator for FancyRegexMatchesIter<'a> {
     type Item = (&'a str, &'a str);
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (k, &v) in self.names.by_ref() {
-            if let Some(m) = self.captures.get(v) {
+        for (k, v) in self.names.by_ref() {
+            if let Some(m) = v.iter().rev().find_map(|&idx| self.captures.get(idx)) {
                 return Some((k.as_str(), m.as_str()));
             }
         }
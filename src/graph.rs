@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::pattern_parser::{grok_split, GrokComponent};
+use crate::{Grok, Scope};
+
+/// The graph of references between the patterns of a [`Grok`], as returned by
+/// [`Grok::graph`].
+///
+/// Global patterns are named as they were added, while patterns of a namespace
+/// are qualified as `namespace::NAME`. The patterns of built-in namespaces are
+/// only included when a registered pattern refers to them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatternGraph {
+    references: BTreeMap<String, BTreeSet<String>>,
+    referenced_by: BTreeMap<String, BTreeSet<String>>,
+    missing: BTreeMap<String, BTreeSet<String>>,
+    cycles: Vec<Vec<String>>,
+}
+
+impl PatternGraph {
+    /// Returns the names of every pattern in the graph.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.references.keys().map(String::as_str)
+    }
+
+    /// Returns the patterns that the given pattern refers to directly, or
+    /// `None` if the pattern is not in the graph.
+    pub fn references(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.references.get(name)
+    }
+
+    /// Returns the patterns that refer directly to the given pattern.
+    pub fn referenced_by(&self, name: &str) -> BTreeSet<&str> {
+        self.referenced_by
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns every pattern that the given pattern expands into, directly or
+    /// through other patterns.
+    pub fn dependencies(&self, name: &str) -> BTreeSet<&str> {
+        Self::reachable(&self.references, name)
+    }
+
+    /// Returns every pattern that expands into the given pattern, directly or
+    /// through other patterns.
+    pub fn dependents(&self, name: &str) -> BTreeSet<&str> {
+        Self::reachable(&self.referenced_by, name)
+    }
+
+    /// Returns the references that could not be resolved, by the name of the
+    /// pattern that makes them.
+    pub fn missing(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.missing
+    }
+
+    /// Returns the groups of patterns that refer to each other in a cycle,
+    /// and so cannot be compiled. Each group is sorted by name.
+    pub fn cycles(&self) -> &[Vec<String>] {
+        &self.cycles
+    }
+
+    /// Renders the graph in the Graphviz DOT language. Patterns in a cycle are
+    /// drawn in red, and missing patterns with dashed lines.
+    pub fn to_dot(&self) -> String {
+        let cyclic = self.cycles.iter().flatten().collect::<BTreeSet<_>>();
+        let mut dot = String::from("digraph grok {\n");
+        for (name, references) in &self.references {
+            if cyclic.contains(name) {
+                _ = writeln!(dot, "    {:?} [color=red];", name);
+            } else if references.is_empty() && !self.referenced_by.contains_key(name) {
+                _ = writeln!(dot, "    {:?};", name);
+            }
+            for reference in references {
+                _ = writeln!(dot, "    {:?} -> {:?};", name, reference);
+            }
+        }
+        for (name, missing) in &self.missing {
+            for reference in missing {
+                _ = writeln!(dot, "    {:?} [style=dashed];", reference);
+                _ = writeln!(dot, "    {:?} -> {:?} [style=dashed];", name, reference);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a JSON object, with the references of each
+    /// pattern under `patterns`, the unresolved references under `missing`,
+    /// and the cycles under `cycles`.
+    pub fn to_json(&self) -> String {
+        fn write_map(json: &mut String, map: &BTreeMap<String, BTreeSet<String>>) {
+            json.push('{');
+            for (i, (name, references)) in map.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_string(json, name);
+                json.push(':');
+                write_list(json, references);
+            }
+            json.push('}');
+        }
+
+        fn write_list<'a>(json: &mut String, names: impl IntoIterator<Item = &'a String>) {
+            json.push('[');
+            for (i, name) in names.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_string(json, name);
+            }
+            json.push(']');
+        }
+
+        fn write_string(json: &mut String, s: &str) {
+            json.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => json.push_str("\\\""),
+                    '\\' => json.push_str("\\\\"),
+                    c if c.is_control() => _ = write!(json, "\\u{:04x}", c as u32),
+                    c => json.push(c),
+                }
+            }
+            json.push('"');
+        }
+
+        let mut json = String::from("{\"patterns\":");
+        write_map(&mut json, &self.references);
+        json.push_str(",\"missing\":");
+        write_map(&mut json, &self.missing);
+        json.push_str(",\"cycles\":[");
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write_list(&mut json, cycle);
+        }
+        json.push_str("]}");
+        json
+    }
+
+    fn reachable<'a>(
+        edges: &'a BTreeMap<String, BTreeSet<String>>,
+        name: &str,
+    ) -> BTreeSet<&'a str> {
+        let mut reached = BTreeSet::new();
+        let mut pending = edges.get(name).into_iter().flatten().collect::<Vec<_>>();
+        while let Some(next) = pending.pop() {
+            if reached.insert(next.as_str()) {
+                pending.extend(edges.get(next).into_iter().flatten());
+            }
+        }
+        reached
+    }
+
+    /// Finds the strongly connected components with more than one pattern, or
+    /// with a pattern that refers to itself, using Tarjan's algorithm.
+    fn find_cycles(&mut self) {
+        struct Tarjan<'a> {
+            edges: &'a BTreeMap<String, BTreeSet<String>>,
+            index: BTreeMap<&'a str, (usize, usize)>,
+            stack: Vec<&'a str>,
+            on_stack: BTreeSet<&'a str>,
+            cycles: Vec<Vec<String>>,
+        }
+
+        impl<'a> Tarjan<'a> {
+            fn visit(&mut self, name: &'a str) {
+                let index = self.index.len();
+                self.index.insert(name, (index, index));
+                self.stack.push(name);
+                self.on_stack.insert(name);
+
+                for reference in self.edges.get(name).into_iter().flatten() {
+                    let reference = reference.as_str();
+                    let low = match self.index.get(reference) {
+                        None => {
+                            self.visit(reference);
+                            self.index[reference].1
+                        }
+                        Some(&(index, _)) if self.on_stack.contains(reference) => index,
+                        Some(_) => continue,
+                    };
+                    let entry = self.index.get_mut(name).unwrap();
+                    entry.1 = entry.1.min(low);
+                }
+
+                if self.index[name].0 == self.index[name].1 {
+                    let mut component = vec![];
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack.remove(member);
+                        component.push(member.to_string());
+                        if member == name {
+                            break;
+                        }
+                    }
+                    let refers_to_itself = self.edges[name].contains(name);
+                    if component.len() > 1 || refers_to_itself {
+                        component.sort();
+                        self.cycles.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            edges: &self.references,
+            index: BTreeMap::new(),
+            stack: vec![],
+            on_stack: BTreeSet::new(),
+            cycles: vec![],
+        };
+        for name in self.references.keys() {
+            if !tarjan.index.contains_key(name.as_str()) {
+                tarjan.visit(name);
+            }
+        }
+        let mut cycles = tarjan.cycles;
+        cycles.sort();
+        self.cycles = cycles;
+    }
+}
+
+impl Grok {
+    /// Builds the graph of references between the patterns of this instance,
+    /// including those of its parent and of its namespaces.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let graph = grok.graph();
+    /// assert!(graph.dependencies("HOSTPORT").contains("POSINT"));
+    /// assert!(graph.dependents("HOSTNAME").contains("IPORHOST"));
+    /// assert!(graph.cycles().is_empty());
+    /// ```
+    pub fn graph(&self) -> PatternGraph {
+        let mut pending = vec![];
+        for name in self.local_names(Scope::Global) {
+            pending.push((name.to_string(), Scope::Global));
+        }
+        let namespaces = self
+            .layers()
+            .flat_map(|grok| grok.namespaces.keys())
+            .collect::<BTreeSet<_>>();
+        for namespace in namespaces {
+            let scope = Scope::Namespace(namespace);
+            for name in self.local_names(scope) {
+                pending.push((name.to_string(), scope));
+            }
+        }
+
+        let mut graph = PatternGraph::default();
+        while let Some((name, scope)) = pending.pop() {
+            let qualified = scope.qualify(&name);
+            if graph.references.contains_key(&qualified) {
+                continue;
+            }
+            let mut references = BTreeSet::new();
+            let mut missing = BTreeSet::new();
+            if let Some(pattern) = self.lookup_local(scope, &name) {
+                self.walk(pattern, scope, &mut references, &mut missing);
+            }
+            for (name, scope) in &references {
+                pending.push((name.clone(), *scope));
+            }
+            let references = references
+                .into_iter()
+                .map(|(name, scope)| scope.qualify(&name))
+                .collect::<BTreeSet<_>>();
+            for reference in &references {
+                graph
+                    .referenced_by
+                    .entry(reference.clone())
+                    .or_default()
+                    .insert(qualified.clone());
+            }
+            if !missing.is_empty() {
+                graph.missing.insert(qualified.clone(), missing);
+            }
+            graph.references.insert(qualified, references);
+        }
+        graph.find_cycles();
+        graph
+    }
+
+    /// Returns every pattern that the given pattern expands into, directly or
+    /// through other patterns. See [`Grok::graph`].
+    pub fn dependencies(&self, name: &str) -> BTreeSet<String> {
+        let graph = self.graph();
+        let dependencies = graph.dependencies(name);
+        dependencies.into_iter().map(str::to_string).collect()
+    }
+
+    /// Returns every pattern that expands into the given pattern, directly or
+    /// through other patterns. See [`Grok::graph`].
+    pub fn dependents(&self, name: &str) -> BTreeSet<String> {
+        let graph = self.graph();
+        let dependents = graph.dependents(name);
+        dependents.into_iter().map(str::to_string).collect()
+    }
+
+    /// Collects the references made by a pattern defined in `scope`, resolving
+    /// them as [`Grok::compile`] would.
+    fn walk<'a>(
+        &'a self,
+        pattern: &str,
+        scope: Scope<'a>,
+        references: &mut BTreeSet<(String, Scope<'a>)>,
+        missing: &mut BTreeSet<String>,
+    ) {
+        let mut inline = BTreeSet::new();
+        for component in grok_split(pattern) {
+            let GrokComponent::GrokPattern {
+                name: qualified_name,
+                alias,
+                extract,
+                definition,
+                ..
+            } = component
+            else {
+                continue;
+            };
+            let Some((namespace, name, _)) = self.split_reference(qualified_name, alias, extract)
+            else {
+                missing.insert(qualified_name.to_string());
+                continue;
+            };
+
+            if !definition.is_empty() {
+                inline.insert(name);
+                self.walk(definition, scope, references, missing);
+            } else if let Some(namespace) = namespace {
+                if self.lookup_local(namespace, name).is_some() {
+                    references.insert((name.to_string(), namespace));
+                } else {
+                    missing.insert(qualified_name.to_string());
+                }
+            } else if !inline.contains(name) {
+                match self.resolve(scope, name) {
+                    Some((_, scope)) => _ = references.insert((name.to_string(), scope)),
+                    None => _ = missing.insert(name.to_string()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "%{B} %{C:c} %{D=x} %{D} %{MISSING}");
+        grok.add_pattern("B", "%{C}");
+        grok.add_pattern("C", "c");
+        grok.add_pattern("E", "%{httpd::HTTPD_COMMONLOG}");
+        grok.add_namespace_pattern("acme", "C", "%{B}");
+
+        let graph = grok.graph();
+        assert_eq!(
+            Some(&BTreeSet::from(["B".to_string(), "C".to_string()])),
+            graph.references("A")
+        );
+        assert_eq!(BTreeSet::from(["A", "B"]), graph.referenced_by("C"));
+        assert_eq!(BTreeSet::from(["A", "acme::C"]), graph.referenced_by("B"));
+        assert_eq!(BTreeSet::from(["A", "B", "acme::C"]), graph.dependents("C"));
+        assert_eq!(BTreeSet::from(["B", "C"]), graph.dependencies("A"));
+        assert_eq!(
+            &BTreeMap::from([("A".to_string(), BTreeSet::from(["MISSING".to_string()]))]),
+            graph.missing()
+        );
+        assert!(graph.cycles().is_empty());
+
+        // Built-in namespaces are followed when they are referred to.
+        let dependencies = graph.dependencies("E");
+        assert!(dependencies.contains("httpd::HTTPD_COMMONLOG"));
+        assert!(dependencies.contains("grok::IPORHOST"));
+        assert!(graph.patterns().any(|name| name == "grok::IPORHOST"));
+        assert!(!graph.patterns().any(|name| name == "grok::SYSLOGBASE"));
+    }
+
+    #[test]
+    fn test_graph_cycles() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "%{B}");
+        grok.add_pattern("B", "%{C}");
+        grok.add_pattern("C", "%{A} %{D}");
+        grok.add_pattern("D", "d");
+        grok.add_pattern("SELF", "%{SELF}");
+        grok.add_pattern("INLINE", "%{SELF=x} %{SELF}");
+
+        let graph = grok.graph();
+        assert_eq!(
+            &[
+                vec!["A".to_string(), "B".to_string(), "C".to_string()],
+                vec!["SELF".to_string()],
+            ],
+            graph.cycles()
+        );
+        assert_eq!(Some(&BTreeSet::new()), graph.references("INLINE"));
+        assert_eq!(
+            BTreeSet::from(["A".to_string(), "B".to_string(), "C".to_string()]),
+            grok.dependents("A")
+        );
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "%{B} %{MISSING}");
+        grok.add_pattern("B", "%{B}");
+        grok.add_pattern("C", "c");
+
+        let graph = grok.graph();
+        assert_eq!(
+            r#"digraph grok {
+    "A" -> "B";
+    "B" [color=red];
+    "B" -> "B";
+    "C";
+    "MISSING" [style=dashed];
+    "A" -> "MISSING" [style=dashed];
+}
+"#,
+            graph.to_dot()
+        );
+        assert_eq!(
+            r#"{"patterns":{"A":["B"],"B":["B"],"C":[]},"missing":{"A":["MISSING"]},"cycles":[["B"]]}"#,
+            graph.to_json()
+        );
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-linux-syslog"))]
+    #[test]
+    fn test_default_graph() {
+        let graph = Grok::default().graph();
+        assert!(graph.cycles().is_empty());
+        assert!(graph.missing().is_empty(), "{:?}", graph.missing());
+        let dependencies = graph.dependencies("SYSLOGLINE");
+        assert!(dependencies.contains("SYSLOGBASE2"));
+        assert!(dependencies.contains("IPV4"));
+        assert!(!dependencies.contains("SYSLOGLINE"));
+    }
+}
@@ -0,0 +1,315 @@
+//! Capture history for repeated groups, see
+//! [`Grok::set_capture_history`](crate::Grok::set_capture_history).
+//!
+//! With the built-in Oniguruma engine, the history is recorded by Oniguruma
+//! itself while matching. Otherwise, it is emulated: each repeated group that
+//! contains captures is wrapped in a group capturing the span of the whole
+//! repetition, and the iterations are then matched one by one within that
+//! span. An iteration is matched on its own, so anchors, word boundaries,
+//! look-around and backreferences that reach outside of it do not see the
+//! rest of the text.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use crate::ast::{
+    self, rewrite_children, visit_children, Group, GroupKind, Node, Rewriter, Visitor,
+};
+use crate::{Error, ExpandedPattern, GrokEngine, Matches, Pattern};
+
+/// The name of the group capturing a single iteration.
+const ITERATION: &str = "_h_i";
+
+#[derive(Debug)]
+pub(crate) enum CaptureHistory {
+    /// Recorded by Oniguruma while matching.
+    #[cfg(all(not(feature = "pcre2"), not(feature = "fancy-regex"), feature = "onig"))]
+    Native(crate::onig::OnigHistory),
+    /// Emulated by matching the iterations one by one.
+    Emulated(Emulated),
+}
+
+#[derive(Debug)]
+pub(crate) struct Emulated {
+    /// The pattern with the span of every repetition captured as `_h_N`.
+    pattern: Pattern,
+    repeats: Vec<Repeat>,
+}
+
+#[derive(Debug)]
+struct Repeat {
+    /// The name of the group capturing the span of the repetition.
+    group: String,
+    /// The names of the captures inside the repetition.
+    names: BTreeSet<String>,
+    /// Matches the first iteration of the repetition as `_h_i`, followed by
+    /// the other iterations.
+    iteration: Emulated,
+}
+
+/// A repeated group found in a regular expression.
+struct RawRepeat {
+    group: String,
+    body: String,
+    /// The body without named groups, for the other iterations.
+    tail: String,
+    names: BTreeSet<String>,
+}
+
+impl CaptureHistory {
+    /// Prepares the capture history of an expanded regular expression, or
+    /// returns `None` if it has no repeated captures. The history is recorded
+    /// natively when the built-in engine is Oniguruma, unless the repeated
+    /// captures are beyond the groups Oniguruma can record.
+    pub(crate) fn new(
+        regex: &str,
+        aliases: &HashMap<String, String>,
+        engine: Option<&dyn GrokEngine>,
+    ) -> Result<Option<Self>, Error> {
+        #[cfg(all(not(feature = "pcre2"), not(feature = "fancy-regex"), feature = "onig"))]
+        if engine.is_none() {
+            if let Some(history) = crate::onig::OnigHistory::new(regex, aliases)? {
+                return Ok(Some(Self::Native(history)));
+            }
+        }
+        let (regex, repeats) = wrap_repeats(regex)?;
+        if repeats.is_empty() {
+            return Ok(None);
+        }
+        Emulated::compile(regex, repeats, aliases, engine)
+            .map(|history| Some(Self::Emulated(history)))
+    }
+
+    /// Matches the text, returning the spans of every value captured by the
+    /// groups selected by `is_key`, in the order they appear in the text.
+    pub(crate) fn spans(&self, text: &str, is_key: &dyn Fn(&str) -> bool) -> Vec<Range<usize>> {
+        let mut spans = match self {
+            #[cfg(all(not(feature = "pcre2"), not(feature = "fancy-regex"), feature = "onig"))]
+            Self::Native(history) => history.spans(text, is_key),
+            Self::Emulated(history) => history.spans(text, is_key),
+        };
+        spans.sort_by_key(|span| span.start);
+        spans
+    }
+}
+
+/// Returns whether a quantifier repeats at most once, such as `?` or `{0,1}`,
+/// in which case the captures it repeats have no history.
+fn repeats_at_most_once(quantifier: &str) -> bool {
+    if quantifier.starts_with('?') {
+        return true;
+    }
+    let Some(bounds) = quantifier
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(bounds, _)| bounds)
+    else {
+        return false;
+    };
+    let max = bounds.split_once(',').map_or(bounds, |(_, max)| max);
+    max.trim().parse::<usize>().is_ok_and(|max| max <= 1)
+}
+
+impl Emulated {
+    fn compile(
+        regex: String,
+        repeats: Vec<RawRepeat>,
+        aliases: &HashMap<String, String>,
+        engine: Option<&dyn GrokEngine>,
+    ) -> Result<Self, Error> {
+        let expanded = ExpandedPattern {
+            regex,
+            aliases: aliases.clone(),
+            ..Default::default()
+        };
+        let pattern = Pattern::new(expanded, engine)?;
+        let repeats = repeats
+            .into_iter()
+            .map(|repeat| {
+                // The other iterations must fill the rest of the span, so that
+                // the first iteration is split off like in the original match
+                let regex = format!(r"\A(?<{ITERATION}>{})(?:{})*\z", repeat.body, repeat.tail);
+                let (regex, nested) = wrap_repeats(&regex)?;
+                let names = repeat.names.into_iter();
+                Ok(Repeat {
+                    group: repeat.group,
+                    names: names
+                        .map(|name| aliases.get(&name).cloned().unwrap_or(name))
+                        .collect(),
+                    iteration: Self::compile(regex, nested, aliases, engine)?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { pattern, repeats })
+    }
+
+    fn spans(&self, text: &str, is_key: &dyn Fn(&str) -> bool) -> Vec<Range<usize>> {
+        let mut spans = vec![];
+        if let Some(matches) = self.pattern.match_against(text) {
+            self.collect(&matches, text, 0, is_key, &mut spans);
+        }
+        spans
+    }
+
+    fn collect(
+        &self,
+        matches: &Matches,
+        text: &str,
+        offset: usize,
+        is_key: &dyn Fn(&str) -> bool,
+        spans: &mut Vec<Range<usize>>,
+    ) {
+        let span = |value: &str| {
+            let start = value.as_ptr() as usize - text.as_ptr() as usize;
+            start..start + value.len()
+        };
+
+        for key in self.pattern.capture_names().filter(|key| is_key(key)) {
+            if self.repeats.iter().any(|repeat| repeat.names.contains(key)) {
+                continue;
+            }
+            for (_, value) in matches.values(key) {
+                let span = span(value);
+                spans.push(offset + span.start..offset + span.end);
+            }
+        }
+
+        for repeat in &self.repeats {
+            if !repeat.names.iter().any(|key| is_key(key)) {
+                continue;
+            }
+            let Some(Range { start, end }) = matches.get(&repeat.group).map(span) else {
+                continue;
+            };
+            let mut start = start;
+            while start < end {
+                let iteration = &text[start..end];
+                let Some(matches) = repeat.iteration.pattern.match_against(iteration) else {
+                    break;
+                };
+                let len = matches.get(ITERATION).map_or(0, str::len);
+                repeat
+                    .iteration
+                    .collect(&matches, iteration, offset + start, is_key, spans);
+                if len == 0 {
+                    break;
+                }
+                start += len;
+            }
+        }
+    }
+}
+
+/// Wraps every repeated group of a regular expression that contains named
+/// groups in a named group `_h_N`, without looking into nested repetitions.
+fn wrap_repeats(regex: &str) -> Result<(String, Vec<RawRepeat>), Error> {
+    let ast = ast::parse(regex)
+        .map_err(|e| Error::GenericCompilationFailure(format!("{e}:\n{regex}")))?;
+    let mut wrap = WrapRepeats(vec![]);
+    let regex = ast.rewrite(&mut wrap).to_string();
+    Ok((regex, wrap.0))
+}
+
+struct WrapRepeats(Vec<RawRepeat>);
+
+impl<'a> Rewriter<'a> for WrapRepeats {
+    fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+        match node {
+            Node::Repeat { node, quantifier } if !repeats_at_most_once(&quantifier) => {
+                let mut names = GroupNames(BTreeSet::new());
+                names.visit(&node);
+                if names.0.is_empty() {
+                    return Node::Repeat { node, quantifier };
+                }
+                let group = format!("_h_{}", self.0.len());
+                self.0.push(RawRepeat {
+                    group: group.clone(),
+                    body: node.to_string(),
+                    tail: Unname.rewrite((*node).clone()).to_string(),
+                    names: names.0,
+                });
+                Node::Group(Group {
+                    open: format!("(?<{group}>").into(),
+                    kind: GroupKind::Named(group.into()),
+                    nodes: vec![Node::Repeat { node, quantifier }],
+                })
+            }
+            node => rewrite_children(self, node),
+        }
+    }
+}
+
+/// Returns the names of the named groups inside repeated groups.
+#[cfg(feature = "onig")]
+pub(crate) fn repeated_names(ast: &ast::Ast) -> BTreeSet<String> {
+    let mut names = RepeatedNames(GroupNames(BTreeSet::new()));
+    ast.visit(&mut names);
+    names.0 .0
+}
+
+/// Collects the names of the named groups inside repeated groups.
+#[cfg(feature = "onig")]
+struct RepeatedNames(GroupNames);
+
+#[cfg(feature = "onig")]
+impl<'a> Visitor<'a> for RepeatedNames {
+    fn visit(&mut self, node: &Node<'a>) {
+        match node {
+            Node::Repeat { node, quantifier } if !repeats_at_most_once(quantifier) => {
+                self.0.visit(node)
+            }
+            node => visit_children(self, node),
+        }
+    }
+}
+
+/// Collects the names of the named groups.
+struct GroupNames(BTreeSet<String>);
+
+impl<'a> Visitor<'a> for GroupNames {
+    fn visit(&mut self, node: &Node<'a>) {
+        if let Node::Group(Group {
+            kind: GroupKind::Named(name),
+            ..
+        }) = node
+        {
+            self.0.insert(name.to_string());
+        }
+        visit_children(self, node);
+    }
+}
+
+/// Turns named groups into non-capturing groups.
+struct Unname;
+
+impl<'a> Rewriter<'a> for Unname {
+    fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+        match rewrite_children(self, node) {
+            Node::Group(Group {
+                kind: GroupKind::Named(_),
+                nodes,
+                ..
+            }) => Node::Group(Group {
+                kind: GroupKind::NonCapturing,
+                open: "(?:".into(),
+                nodes,
+            }),
+            node => node,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_at_most_once() {
+        for quantifier in ["?", "??", "?+", "{0,1}", "{0,1}?", "{1}", "{0}", "{1,1}"] {
+            assert!(repeats_at_most_once(quantifier), "{quantifier}");
+        }
+        for quantifier in ["*", "+?", "{2}", "{0,}", "{1,}", "{0,2}", "{2,3}?"] {
+            assert!(!repeats_at_most_once(quantifier), "{quantifier}");
+        }
+    }
+}
@@ -2,12 +2,21 @@
 
 include!(concat!(env!("OUT_DIR"), "/default_patterns.rs"));
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error as StdError;
 use std::fmt;
-
+use std::ops::Range;
+use std::sync::Arc;
+
+mod ast;
+mod cache;
+#[cfg(feature = "serde")]
+mod de;
+mod engine;
 #[cfg(feature = "fancy-regex")]
 mod fancy_regex;
+mod graph;
+mod history;
 #[cfg(feature = "onig")]
 mod onig;
 #[cfg(feature = "pcre2")]
@@ -15,7 +24,18 @@ mod pcre2;
 #[cfg(feature = "regex")]
 mod regex;
 
+pub mod dialect;
 mod pattern_parser;
+mod schema;
+pub mod testing;
+mod tree;
+
+#[cfg(feature = "serde")]
+pub use de::DeserializeError;
+pub use engine::{CompiledRegex, GrokEngine};
+pub use graph::PatternGraph;
+pub use schema::FieldSchema;
+pub use tree::MatchNode;
 
 // Enable features in the following preferred order. If multiple features are
 // enabled, the first one in the list is used.
@@ -67,47 +87,233 @@ use regex::{
     RegexPattern as InnerPattern, ENGINE,
 };
 
-use crate::pattern_parser::{grok_split, GrokComponent};
+use crate::cache::PatternCache;
+use crate::dialect::TranslationIssue;
+use crate::engine::{CustomMatches, CustomMatchesIter, CustomPattern};
+use crate::history::CaptureHistory;
+use crate::pattern_parser::{grok_split, GrokComponent, GrokPatternError, GrokSplit};
+use crate::tree::Capture;
+
+/// The result of expanding a grok pattern into a plain regular expression.
+///
+/// Every named grok placeholder is expanded into a named group `_n_N`, where
+/// `N` is the index of the placeholder in expansion order. The alias map
+/// relates these group names back to the names (or aliases) used in the
+/// matches.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpandedPattern {
+    regex: String,
+    aliases: HashMap<String, String>,
+    extracts: HashMap<String, String>,
+    dependencies: BTreeSet<String>,
+    captures: Vec<Capture>,
+    /// The byte ranges of the regex that come from built-in definitions,
+    /// which are translated to the dialect of the engine.
+    builtin: Vec<Range<usize>>,
+}
+
+impl ExpandedPattern {
+    /// Returns the assembled regular expression.
+    pub fn regex(&self) -> &str {
+        &self.regex
+    }
+
+    /// Returns the map of generated group names (`_n_N`) to capture names.
+    pub fn aliases(&self) -> &HashMap<String, String> {
+        &self.aliases
+    }
+
+    /// Returns the map of capture names to their extract.
+    pub fn extracts(&self) -> &HashMap<String, String> {
+        &self.extracts
+    }
+
+    /// Returns the names of the patterns that were looked up in the `Grok`
+    /// during the expansion, qualified as `namespace::NAME` when they were
+    /// found in a namespace.
+    ///
+    /// Inline definitions and overrides passed to [`Grok::expand_with`] are
+    /// not included.
+    pub fn dependencies(&self) -> &BTreeSet<String> {
+        &self.dependencies
+    }
+
+    /// Consumes this expansion, returning the regular expression.
+    pub fn into_regex(self) -> String {
+        self.regex
+    }
+}
+
+/// A pattern compiled with the engine selected by the crate features, or with
+/// a [`GrokEngine`].
+#[derive(Debug)]
+enum PatternBackend {
+    Builtin(InnerPattern),
+    Custom(CustomPattern),
+}
+
+/// The matches of a [`PatternBackend`].
+enum MatchesBackend<'a> {
+    Builtin(MatchesInner<'a>),
+    Custom(CustomMatches<'a>),
+}
+
+/// An iterator over the matches of a [`PatternBackend`].
+enum MatchesIterBackend<'a> {
+    Builtin(MatchesIterInner<'a>),
+    Custom(CustomMatchesIter<'a>),
+}
 
 /// The `Pattern` represents a compiled regex, ready to be matched against arbitrary text.
 pub struct Pattern {
-    inner: InnerPattern,
+    inner: PatternBackend,
     extracts: HashMap<String, String>,
-    #[cfg(test)]
-    text: String,
+    regex: String,
+    history: Option<Box<CaptureHistory>>,
+    captures: Vec<Capture>,
+    approximations: Vec<TranslationIssue>,
 }
 
 impl Pattern {
-    /// Creates a new pattern from a raw regex string and an alias map to identify the
-    /// fields properly.
+    /// Creates a new pattern from an expanded grok pattern, translating the
+    /// parts that come from built-in definitions to the dialect of the given
+    /// engine, or of the active engine.
     #[inline(always)]
-    fn new(
-        regex: &str,
-        alias: HashMap<String, String>,
-        extracts: HashMap<String, String>,
-    ) -> Result<Self, Error> {
-        let inner = InnerPattern::new(regex, &alias)?;
+    fn new(expanded: ExpandedPattern, engine: Option<&dyn GrokEngine>) -> Result<Self, Error> {
+        let dialect = engine.map_or(ENGINE.into(), |engine| engine.dialect());
+        let translation = dialect::translate_ranges(&expanded.regex, &expanded.builtin, dialect)
+            .map_err(|e| Error::RegexTranslationFailed(format!("{e}:\n{}", expanded.regex)))?;
+        let approximations = translation.approximations().to_vec();
+        let regex = translation.into_regex();
+        let inner = match engine {
+            Some(engine) => {
+                PatternBackend::Custom(CustomPattern::new(engine, &regex, &expanded.aliases)?)
+            }
+            None => PatternBackend::Builtin(InnerPattern::new(&regex, &expanded.aliases)?),
+        };
         Ok(Self {
             inner,
-            extracts,
-            #[cfg(test)]
-            text: regex.to_string(),
+            extracts: expanded.extracts,
+            regex,
+            history: None,
+            captures: expanded.captures,
+            approximations,
         })
     }
 
+    /// Returns the constructs of the built-in definitions that were replaced
+    /// by an approximation when translating them to the dialect of the engine,
+    /// such as POSIX bracket classes, which only match ASCII characters once
+    /// translated. Their ranges refer to the regex returned by
+    /// [`Grok::expand`].
+    ///
+    /// Regular expressions written in a pattern or added with
+    /// [`Grok::add_pattern`] are passed to the engine unchanged, and never
+    /// approximated.
+    pub fn approximations(&self) -> &[TranslationIssue] {
+        &self.approximations
+    }
+
     /// Matches this compiled `Pattern` against the text and returns the matches.
     #[inline(always)]
     pub fn match_against<'a>(&'a self, text: &'a str) -> Option<Matches<'a>> {
+        let inner = match &self.inner {
+            PatternBackend::Builtin(inner) => MatchesBackend::Builtin(inner.match_against(text)?),
+            PatternBackend::Custom(inner) => MatchesBackend::Custom(inner.match_against(text)?),
+        };
         Some(Matches {
-            inner: self.inner.match_against(text)?,
+            inner,
+            text,
             pattern: self,
         })
     }
 
+    /// Returns whether this `Pattern` matches somewhere in the text.
+    ///
+    /// This skips the capture groups, through the cheapest path of the engine,
+    /// and is faster than [`Pattern::match_against`] when the values are not
+    /// needed.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let pattern = grok.compile("%{IP:client} %{WORD:verb}", false).unwrap();
+    /// assert!(pattern.is_match("10.0.0.1 GET"));
+    /// assert!(!pattern.is_match("10.0.0.1 -"));
+    /// ```
+    #[inline(always)]
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.inner {
+            PatternBackend::Builtin(inner) => inner.is_match(text),
+            PatternBackend::Custom(inner) => inner.is_match(text),
+        }
+    }
+
+    /// Returns the span of the first match of this `Pattern` in the text,
+    /// without the values of its captures.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let pattern = grok.compile("%{INT:status}", false).unwrap();
+    /// assert_eq!(Some(7..10), pattern.find("status 404"));
+    /// assert_eq!(None, pattern.find("status unknown"));
+    /// ```
+    #[inline(always)]
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        match &self.inner {
+            PatternBackend::Builtin(inner) => inner.find(text),
+            PatternBackend::Custom(inner) => inner.find(text),
+        }
+    }
+
+    /// Matches this compiled `Pattern` against the text and deserializes the
+    /// matches into `T`, returning `Ok(None)` if the text does not match.
+    ///
+    /// Captured values are parsed from their text when `T` asks for a
+    /// non-string type (numbers, `bool`, `char` or unit enum variants), and
+    /// captures that did not participate in the match are absent, so `Option`
+    /// fields deserialize as `None`.
+    #[cfg(feature = "serde")]
+    pub fn match_into<T: serde::de::DeserializeOwned>(
+        &self,
+        text: &str,
+    ) -> Result<Option<T>, DeserializeError> {
+        match self.match_against(text) {
+            Some(matches) => matches.deserialize().map(Some),
+            None => Ok(None),
+        }
+    }
+
     /// Returns all names this `Pattern` captures.
     #[inline(always)]
     pub fn capture_names(&self) -> impl Iterator<Item = &str> {
-        self.inner.capture_names()
+        let (builtin, custom) = match &self.inner {
+            PatternBackend::Builtin(inner) => (Some(inner.capture_names()), None),
+            PatternBackend::Custom(inner) => (None, Some(inner.capture_names())),
+        };
+        builtin
+            .into_iter()
+            .flatten()
+            .chain(custom.into_iter().flatten())
+    }
+
+    /// Returns the fields this `Pattern` captures, in the order they appear in
+    /// it, with the pattern that captures them, whether they may be absent
+    /// from a match, their extract and the patterns they were found in.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let pattern = grok
+    ///     .compile("%{IP:client} (?:%{NUMBER:bytes:int}|-)", true)
+    ///     .unwrap();
+    /// let schema = pattern.schema();
+    /// assert_eq!("client", schema[0].name());
+    /// assert_eq!(Some("IP"), schema[0].pattern());
+    /// assert!(!schema[0].is_optional());
+    /// assert_eq!(Some("int"), schema[1].extract());
+    /// assert!(schema[1].is_optional());
+    /// ```
+    pub fn schema(&self) -> Vec<FieldSchema> {
+        schema::fields(self)
     }
 
     /// Returns the extract for the given name.
@@ -115,19 +321,30 @@ impl Pattern {
     pub fn get_extract(&self, name: &str) -> Option<&str> {
         self.extracts.get(name).map(|s| s.as_str())
     }
+
+    /// Returns the regular expression this `Pattern` was compiled from, after
+    /// translation to the dialect of the active engine.
+    #[inline(always)]
+    pub fn regex_source(&self) -> &str {
+        &self.regex
+    }
 }
 
 impl std::fmt::Debug for Pattern {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let regex: &dyn std::fmt::Debug = match &self.inner {
+            PatternBackend::Builtin(inner) => &inner.regex,
+            PatternBackend::Custom(inner) => &inner.regex,
+        };
         if f.alternate() {
             f.debug_struct("Pattern")
-                .field("regex", &self.inner.regex)
+                .field("regex", regex)
                 .field("extracts", &self.extracts)
                 .field("capture_names", &self.capture_names().collect::<Vec<_>>())
                 .finish()
         } else {
             f.debug_struct("Pattern")
-                .field("regex", &self.inner.regex)
+                .field("regex", regex)
                 .field(
                     "extracts",
                     &format!("{{ {:?} extract(s) }}", self.extracts.len()),
@@ -143,21 +360,104 @@ impl std::fmt::Debug for Pattern {
 
 /// The `Matches` represent matched results from a `Pattern` against a provided text.
 pub struct Matches<'a> {
-    inner: MatchesInner<'a>,
+    inner: MatchesBackend<'a>,
+    text: &'a str,
     pattern: &'a Pattern,
 }
 
 impl<'a> Matches<'a> {
     /// Gets the value for the name (or) alias if found, `None` otherwise.
+    ///
+    /// When several groups have the same name, such as a named group of a
+    /// pattern with the same name as an alias, the value of the last of them
+    /// that took part in the match is returned, whatever the engine.
     #[inline(always)]
     pub fn get(&self, name: &str) -> Option<&str> {
-        self.inner.get(name)
+        match &self.inner {
+            MatchesBackend::Builtin(inner) => inner.get(name),
+            MatchesBackend::Custom(inner) => inner.get(name),
+        }
+    }
+
+    /// Gets the values of every group with the name (or) alias that took part
+    /// in the match, in the order of the groups in the pattern.
+    ///
+    /// This includes the values of an alias used more than once, which are
+    /// otherwise named `name[1]`, `name[2]` and so on.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let pattern = grok.compile("%{INT:n},%{INT:n}(?:,%{INT:n})?", true).unwrap();
+    /// let matches = pattern.match_against("1,2").unwrap();
+    /// assert_eq!(vec!["1", "2"], matches.get_all("n"));
+    /// assert_eq!(Some("2"), matches.get("n[1]"));
+    /// ```
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        let mut values = vec![];
+        for key in self.pattern.capture_names() {
+            if is_capture_of(key, name) {
+                values.extend(self.values(key));
+            }
+        }
+        values.sort_by_key(|(index, _)| *index);
+        values.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Gets the values of every iteration of the groups with the name (or)
+    /// alias, in the order they appear in the text, when the pattern was
+    /// compiled with [`Grok::set_capture_history`]. Otherwise, this is the same
+    /// as [`Matches::get_all`].
+    ///
+    /// ```
+    /// let mut grok = grok::Grok::default();
+    /// grok.set_capture_history(true);
+    /// let pattern = grok.compile(r"(?:%{WORD:key}=\S+ ?)+", true).unwrap();
+    /// let matches = pattern.match_against("a=1 b=2 c=3").unwrap();
+    /// assert_eq!(Some("c"), matches.get("key"));
+    /// assert_eq!(vec!["a", "b", "c"], matches.history("key"));
+    /// ```
+    pub fn history(&self, name: &str) -> Vec<&str> {
+        let Some(history) = &self.pattern.history else {
+            return self.get_all(name);
+        };
+        let spans = history.spans(self.text, &|key| is_capture_of(key, name));
+        spans.into_iter().map(|span| &self.text[span]).collect()
+    }
+
+    /// Returns the placeholders that took part in the match as a tree, following
+    /// the nesting of the patterns they expand to. This tells which of the
+    /// alternatives of a pattern like `IP` matched.
+    ///
+    /// Only placeholders that capture are included, so the pattern must be
+    /// compiled with `with_alias_only` set to `false` to see all of them.
+    ///
+    /// ```
+    /// let grok = grok::Grok::default();
+    /// let pattern = grok.compile("%{IP:client}", false).unwrap();
+    /// let matches = pattern.match_against("10.0.0.1").unwrap();
+    /// let tree = matches.tree();
+    /// assert_eq!("IP", tree[0].pattern());
+    /// assert_eq!(Some("client"), tree[0].alias());
+    /// assert_eq!(0..8, tree[0].span());
+    /// assert_eq!("IPV4", tree[0].children()[0].pattern());
+    /// ```
+    pub fn tree(&self) -> Vec<MatchNode<'a>> {
+        tree::build(self, &self.pattern.captures)
+    }
+
+    /// Gets the values of every group with exactly this name, along with
+    /// their index.
+    fn values(&self, key: &str) -> Vec<(usize, &str)> {
+        match &self.inner {
+            MatchesBackend::Builtin(inner) => inner.get_all(key),
+            MatchesBackend::Custom(inner) => inner.get_all(key),
+        }
     }
 
     /// Returns a tuple of key/value with all the matches found.
     #[inline(always)]
     pub fn iter(&'a self) -> impl Iterator<Item = (&'a str, &'a str)> {
-        self.inner.iter()
+        self.into_iter()
     }
 
     /// Collects the matches into a collection supporting `FromIterator`.
@@ -166,6 +466,15 @@ impl<'a> Matches<'a> {
         self.iter().collect()
     }
 
+    /// Deserializes the matches into `T`, which may borrow from the matched
+    /// text.
+    ///
+    /// See [`Pattern::match_into`] for how captured values are converted.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::Deserialize<'a>>(&'a self) -> Result<T, DeserializeError> {
+        T::deserialize(de::MatchesDeserializer::new(self))
+    }
+
     /// Returns the number of matches.
     #[cfg(test)]
     #[inline(always)]
@@ -183,8 +492,7 @@ impl<'a> Matches<'a> {
 
 impl<'a> std::fmt::Debug for Matches<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let iter = self.inner.into_iter();
-        f.debug_map().entries(iter).finish()
+        f.debug_map().entries(self).finish()
     }
 }
 
@@ -192,15 +500,17 @@ impl<'a> IntoIterator for &'a Matches<'a> {
     type Item = (&'a str, &'a str);
     type IntoIter = MatchesIter<'a>;
     fn into_iter(self) -> Self::IntoIter {
-        MatchesIter {
-            inner: self.inner.into_iter(),
-        }
+        let inner = match &self.inner {
+            MatchesBackend::Builtin(inner) => MatchesIterBackend::Builtin(inner.into_iter()),
+            MatchesBackend::Custom(inner) => MatchesIterBackend::Custom(inner.into_iter()),
+        };
+        MatchesIter { inner }
     }
 }
 
 /// An `Iterator` over all matches, accessible via `Matches`.
 pub struct MatchesIter<'a> {
-    inner: MatchesIterInner<'a>,
+    inner: MatchesIterBackend<'a>,
 }
 
 impl<'a> Iterator for MatchesIter<'a> {
@@ -208,7 +518,10 @@ impl<'a> Iterator for MatchesIter<'a> {
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next()
+        match &mut self.inner {
+            MatchesIterBackend::Builtin(inner) => inner.next(),
+            MatchesIterBackend::Custom(inner) => inner.next(),
+        }
     }
 }
 
@@ -220,6 +533,34 @@ impl<'a> Iterator for MatchesIter<'a> {
 ))]
 compile_error!("No regex engine selected. Please enable one of the following features: fancy-regex, onig, regex");
 
+/// Returns whether a capture name is `name`, or `name[N]` for an alias used
+/// more than once.
+fn is_capture_of(key: &str, name: &str) -> bool {
+    let index = key
+        .strip_prefix(name)
+        .and_then(|suffix| suffix.strip_prefix('[')?.strip_suffix(']'));
+    key == name || index.is_some_and(|index| index.parse::<usize>().is_ok())
+}
+
+/// Maps the names of the capture groups of a compiled regex, given with their
+/// indices, to the indices of every group with that name in ascending order.
+/// The generated group names are replaced by their alias.
+pub(crate) fn group_names<'a>(
+    groups: impl IntoIterator<Item = (usize, &'a str)>,
+    alias: &HashMap<String, String>,
+) -> BTreeMap<String, Vec<usize>> {
+    let mut names: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, name) in groups {
+        let name = alias.get(name).map_or(name, |s| s);
+        names.entry(name.to_string()).or_default().push(index);
+    }
+    names
+        .values_mut()
+        .for_each(|indices| indices.sort_unstable());
+    names
+}
+
+/// The default limit of nested patterns while compiling.
 const MAX_RECURSION: usize = 1024;
 
 /// Returns the default patterns, also used by the default constructor of `Grok`.
@@ -227,37 +568,453 @@ pub fn patterns<'a>() -> &'a [(&'a str, &'a str)] {
     PATTERNS
 }
 
+/// Returns the linear-time replacements for the default patterns that rely on
+/// look-around, which are used by the default constructor of `Grok` when the
+/// `regex` engine is active.
+///
+/// For more information, see the [`mod@patterns::linear`] module.
+pub fn linear_patterns<'a>() -> &'a [(&'a str, &'a str)] {
+    LINEAR_PATTERNS
+}
+
+/// A set of built-in patterns that `Grok` can be created with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PatternSet {
+    /// The original patterns, whose captures use short names such as
+    /// `clientip` and `verb`. See the [`mod@patterns`] module.
+    #[default]
+    Legacy,
+    /// Patterns whose captures use Elastic Common Schema field names such as
+    /// `[source][address]` and `[http][request][method]`. See the
+    /// [`mod@patterns::ecs_v1`] module.
+    EcsV1,
+}
+
+impl PatternSet {
+    /// Returns the patterns of this set.
+    pub fn patterns<'a>(self) -> &'a [(&'a str, &'a str)] {
+        match self {
+            PatternSet::Legacy => PATTERNS,
+            PatternSet::EcsV1 => ECS_V1_PATTERNS,
+        }
+    }
+
+    /// Returns the linear-time replacements for the patterns of this set that
+    /// are specific to it. The replacements returned by [`linear_patterns`]
+    /// apply to every set.
+    pub fn linear_patterns<'a>(self) -> &'a [(&'a str, &'a str)] {
+        match self {
+            PatternSet::Legacy => LINEAR_PATTERNS,
+            PatternSet::EcsV1 => ECS_V1_LINEAR_PATTERNS,
+        }
+    }
+
+    fn patterns_cow(self) -> &'static [(Cow<'static, str>, Cow<'static, str>)] {
+        match self {
+            PatternSet::Legacy => PATTERNS_COW,
+            PatternSet::EcsV1 => ECS_V1_PATTERNS_COW,
+        }
+    }
+
+    fn linear_patterns_cow(self) -> &'static [(Cow<'static, str>, Cow<'static, str>)] {
+        match self {
+            PatternSet::Legacy => LINEAR_PATTERNS_COW,
+            PatternSet::EcsV1 => ECS_V1_LINEAR_PATTERNS_COW,
+        }
+    }
+}
+
+/// The generated tables of a `PatternGroup`.
+struct GroupTables {
+    name: &'static str,
+    namespace: &'static str,
+    enabled: bool,
+    dependencies: &'static [PatternGroup],
+    legacy: &'static [(Cow<'static, str>, Cow<'static, str>)],
+    ecs_v1: &'static [(Cow<'static, str>, Cow<'static, str>)],
+}
+
+impl PatternGroup {
+    /// Returns the name of the group, which is the stem of its pattern file
+    /// (eg: `linux-syslog`).
+    pub fn name(self) -> &'static str {
+        self.tables().name
+    }
+
+    /// Returns the namespace of the group, which is its name with `-` replaced
+    /// by `_` (eg: `linux_syslog`). Its patterns can always be referred to as
+    /// `%{namespace::NAME}`, see [`Grok::add_namespace_pattern`].
+    pub fn namespace(self) -> &'static str {
+        self.tables().namespace
+    }
+
+    /// Returns the group with the given namespace.
+    pub fn from_namespace(namespace: &str) -> Option<PatternGroup> {
+        PatternGroup::ALL
+            .iter()
+            .copied()
+            .find(|group| group.namespace() == namespace)
+    }
+
+    /// Returns true if the patterns of this group are compiled in.
+    ///
+    /// This is always the case unless the `select-patterns` feature is
+    /// enabled, in which case only the groups enabled with their
+    /// `patterns-<name>` feature, and the groups they depend on, are compiled
+    /// in.
+    pub fn is_enabled(self) -> bool {
+        self.tables().enabled
+    }
+
+    /// Returns the groups whose patterns the patterns of this group refer to.
+    pub fn dependencies(self) -> &'static [PatternGroup] {
+        self.tables().dependencies
+    }
+
+    /// Returns the patterns that this group defines in the given set, which
+    /// is empty if the group is not enabled or not part of the set.
+    pub fn patterns(self, set: PatternSet) -> impl Iterator<Item = (&'static str, &'static str)> {
+        self.patterns_cow(set)
+            .iter()
+            .map(|(name, pattern)| (name.as_ref(), pattern.as_ref()))
+    }
+
+    fn patterns_cow(self, set: PatternSet) -> &'static [(Cow<'static, str>, Cow<'static, str>)] {
+        match set {
+            PatternSet::Legacy => self.tables().legacy,
+            PatternSet::EcsV1 => self.tables().ecs_v1,
+        }
+    }
+
+    /// Returns the definition of `name` in this group, replaced by its
+    /// linear-time equivalent when the `regex` engine is active.
+    fn definition(self, set: PatternSet, name: &str) -> Option<&'static str> {
+        let find = |table: &'static [(Cow<'static, str>, Cow<'static, str>)]| {
+            let index = table.binary_search_by(|(key, _)| (**key).cmp(name)).ok()?;
+            Some(table[index].1.as_ref())
+        };
+        let definition = find(self.patterns_cow(set))?;
+        if ENGINE == Engine::Regex {
+            if let Some(linear) =
+                find(set.linear_patterns_cow()).or_else(|| find(LINEAR_PATTERNS_COW))
+            {
+                return Some(linear);
+            }
+        }
+        Some(definition)
+    }
+}
+
+impl fmt::Display for PatternGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where the definition of a pattern comes from, as returned by
+/// [`Grok::pattern_origin`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PatternOrigin {
+    /// The pattern is one of the built-in patterns of this crate.
+    Builtin,
+    /// The pattern was added with [`Grok::add_pattern`] or a similar method.
+    User,
+}
+
+/// A definition that hides another definition of the same name, as reported
+/// by [`Grok::warnings`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShadowWarning {
+    /// A pattern replaced an existing pattern of the same name.
+    Pattern {
+        /// The namespace of the pattern, or `None` for the global patterns.
+        namespace: Option<String>,
+        /// The name of the pattern.
+        name: String,
+    },
+    /// A namespace hides the built-in namespace of the same name.
+    Namespace {
+        /// The name of the namespace.
+        namespace: String,
+    },
+    /// A pattern of an imported namespace is hidden in the importing
+    /// namespace, by a pattern of the namespace itself or of an earlier
+    /// import.
+    Import {
+        /// The importing namespace.
+        namespace: String,
+        /// The imported namespace.
+        import: String,
+        /// The name of the hidden pattern.
+        name: String,
+    },
+}
+
+impl fmt::Display for ShadowWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShadowWarning::Pattern {
+                namespace: None,
+                name,
+            } => write!(f, "Pattern {} replaces an existing definition", name),
+            ShadowWarning::Pattern {
+                namespace: Some(namespace),
+                name,
+            } => write!(
+                f,
+                "Pattern {}::{} replaces an existing definition",
+                namespace, name
+            ),
+            ShadowWarning::Namespace { namespace } => write!(
+                f,
+                "Namespace {} hides the built-in namespace of the same name",
+                namespace
+            ),
+            ShadowWarning::Import {
+                namespace,
+                import,
+                name,
+            } => write!(
+                f,
+                "Pattern {}::{} is hidden in namespace {}",
+                import, name, namespace
+            ),
+        }
+    }
+}
+
+/// The patterns and imports of a namespace added to a `Grok`.
+#[derive(Clone, Debug, Default)]
+struct Namespace {
+    patterns: BTreeMap<String, String>,
+    imports: Vec<String>,
+}
+
+/// Where a pattern is defined, and so where the patterns it refers to are
+/// looked up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Scope<'a> {
+    /// The global patterns.
+    Global,
+    /// The namespace of a built-in group.
+    Builtin(PatternGroup),
+    /// A namespace added to the `Grok`.
+    Namespace(&'a str),
+}
+
+impl Scope<'_> {
+    /// Returns the name of a pattern defined in this scope, qualified by its
+    /// namespace.
+    fn qualify(self, name: &str) -> String {
+        match self {
+            Scope::Global => name.to_string(),
+            Scope::Builtin(group) => format!("{}::{name}", group.namespace()),
+            Scope::Namespace(namespace) => format!("{namespace}::{name}"),
+        }
+    }
+}
+
+/// A placeholder as written, before it is resolved by
+/// [`Grok::resolve_reference`].
+struct Reference<'r> {
+    /// The name of the pattern, possibly with its namespace.
+    name: &'r str,
+    alias: &'r str,
+    extract: &'r str,
+    /// Whether the placeholder has its own inline definition.
+    has_definition: bool,
+}
+
+/// What a placeholder refers to, as found by [`Grok::resolve_reference`].
+enum Target<'a> {
+    /// The placeholder has its own inline definition.
+    Definition,
+    /// An inline definition made earlier in the same pattern.
+    Inline,
+    /// A named pattern, with the scope its references are looked up in and the
+    /// name it is known by in cycles.
+    Named {
+        pattern: &'a str,
+        scope: Scope<'a>,
+        key: String,
+        /// Whether the pattern is replaced for this expansion only.
+        is_override: bool,
+        /// Whether the pattern is one of the built-in definitions.
+        is_builtin: bool,
+    },
+}
+
+/// A pattern being expanded by [`Grok::compile_regex`].
+struct Frame<'a> {
+    /// The components of the pattern left to expand.
+    components: GrokSplit<'a>,
+    /// The inline definitions made so far in the pattern.
+    definitions: BTreeMap<&'a str, &'a str>,
+    /// The scope the references of the pattern are looked up in.
+    scope: Scope<'a>,
+    /// The name the pattern is known by in cycles, `None` for an inline
+    /// definition.
+    key: Option<String>,
+    /// The capture of the placeholder that expanded into this pattern.
+    capture: Option<usize>,
+    /// Whether the pattern is one of the built-in definitions.
+    is_builtin: bool,
+}
+
 /// Grok pattern parser.
 ///
 /// This API is currently unstable and may be subject to change.
 pub mod parser {
+    pub use crate::ast::*;
     pub use crate::pattern_parser::*;
 }
 
 /// The `Grok` struct is the main entry point into using this library.
+///
+/// A `Grok` may be layered on top of a shared parent with
+/// [`Grok::with_parent`], in which case it only stores its own additions.
 #[derive(Clone, Debug)]
 pub struct Grok {
     #[allow(unused)]
     engine: Engine,
+    set: PatternSet,
+    parent: Option<Arc<Grok>>,
     patterns: BTreeMap<Cow<'static, str>, Cow<'static, str>>,
+    namespaces: BTreeMap<String, Namespace>,
+    warnings: Vec<ShadowWarning>,
+    cache: PatternCache,
+    recursion_limit: usize,
+    custom_engine: Option<Arc<dyn GrokEngine>>,
+    capture_history: bool,
 }
 
 impl Grok {
     /// Creates a new `Grok` instance with no patterns.
+    ///
+    /// The built-in namespaces of the legacy set remain available, see
+    /// [`Grok::add_namespace_pattern`].
     pub const fn empty() -> Self {
         Self {
             engine: ENGINE,
+            set: PatternSet::Legacy,
+            parent: None,
             patterns: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
+            warnings: Vec::new(),
+            cache: PatternCache::new(),
+            recursion_limit: MAX_RECURSION,
+            custom_engine: None,
+            capture_history: false,
         }
     }
 
     /// Creates a new `Grok` instance and loads all the default patterns.
     ///
+    /// When the `regex` engine is active, the patterns that rely on
+    /// look-around are replaced by their [`linear_patterns`] equivalents.
+    ///
     /// For more information, see the [`mod@patterns`] module.
     pub fn with_default_patterns() -> Self {
+        Self::with_pattern_set(PatternSet::Legacy)
+    }
+
+    /// Creates a new `Grok` instance and loads the patterns of the given set.
+    ///
+    /// When the `regex` engine is active, the patterns that rely on
+    /// look-around are replaced by their linear-time equivalents, as in
+    /// [`Grok::with_default_patterns`].
+    pub fn with_pattern_set(set: PatternSet) -> Self {
+        Self::with_patterns_cow(set, set.patterns_cow().iter().cloned())
+    }
+
+    /// Creates a new `Grok` instance and loads the patterns of the given
+    /// groups from the given set, along with the groups they depend on.
+    ///
+    /// This keeps the patterns of unrelated groups out of the instance, so
+    /// that, for example, a firewall pattern cannot shadow an application
+    /// pattern of the same name.
+    ///
+    /// ```
+    /// use grok::{Grok, PatternGroup, PatternSet};
+    ///
+    /// let grok = Grok::with_pattern_groups(PatternSet::Legacy, [PatternGroup::Httpd]);
+    /// assert!(grok.compile("%{HTTPD_COMMONLOG}", true).is_ok());
+    /// assert!(grok.compile("%{CISCO_ACTION}", true).is_err());
+    /// ```
+    pub fn with_pattern_groups<I: IntoIterator<Item = PatternGroup>>(
+        set: PatternSet,
+        groups: I,
+    ) -> Self {
+        let mut selected = BTreeSet::new();
+        let mut pending = groups.into_iter().collect::<Vec<_>>();
+        while let Some(group) = pending.pop() {
+            if selected.insert(group) {
+                pending.extend_from_slice(group.dependencies());
+            }
+        }
+
+        Self::with_patterns_cow(
+            set,
+            selected
+                .into_iter()
+                .flat_map(|group| group.patterns_cow(set).iter().cloned()),
+        )
+    }
+
+    /// Creates a new `Grok` instance layered on top of `parent`.
+    ///
+    /// The new instance starts out with no patterns of its own: every pattern
+    /// and namespace of `parent` is looked up through the shared reference,
+    /// so creating many children of a large registry is cheap. Patterns added
+    /// to the child, including ones that replace a pattern of the parent,
+    /// only affect the child. The child uses the pattern set of its parent for
+    /// the built-in namespaces.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use grok::Grok;
+    ///
+    /// let base = Arc::new(Grok::default());
+    /// let mut tenant = Grok::with_parent(base.clone());
+    /// tenant.add_pattern("TICKET", "T-%{INT}");
+    /// assert!(tenant.compile("%{TICKET} %{WORD}", true).is_ok());
+    /// assert!(base.compile("%{TICKET}", true).is_err());
+    /// ```
+    pub fn with_parent(parent: Arc<Grok>) -> Self {
         Self {
-            engine: ENGINE,
-            patterns: BTreeMap::from_iter(PATTERNS_COW.iter().cloned()),
+            set: parent.set,
+            recursion_limit: parent.recursion_limit,
+            custom_engine: parent.custom_engine.clone(),
+            capture_history: parent.capture_history,
+            parent: Some(parent),
+            ..Self::empty()
+        }
+    }
+
+    /// Returns the parent this instance is layered on, if any.
+    pub fn parent(&self) -> Option<&Arc<Grok>> {
+        self.parent.as_ref()
+    }
+
+    fn with_patterns_cow<I: IntoIterator<Item = (Cow<'static, str>, Cow<'static, str>)>>(
+        set: PatternSet,
+        patterns: I,
+    ) -> Self {
+        let mut patterns = BTreeMap::from_iter(patterns);
+        if ENGINE == Engine::Regex {
+            for (name, pattern) in LINEAR_PATTERNS_COW.iter().chain(set.linear_patterns_cow()) {
+                if let Some(existing) = patterns.get_mut(name) {
+                    *existing = pattern.clone();
+                }
+            }
+        }
+        Self {
+            set,
+            patterns,
+            ..Self::empty()
         }
     }
 
@@ -272,137 +1029,1028 @@ impl Grok {
     /// of the pattern, and `definition` is the definition of the pattern.
     ///
     /// - `name` is the name of the pattern and is required. It may contain any
-    ///   alphanumeric character, or `_`.
+    ///   alphanumeric character, or `_`, and may be qualified by a namespace as
+    ///   `namespace::name` (see [`Grok::add_namespace_pattern`]).
     /// - `alias` is the alias of the pattern and is optional. It may contain
     ///   any alphanumeric character, or any of `_-[].`. If extract is provided,
     ///   `alias` may be empty.
     /// - `extract` is the extract of the pattern and is optional. It may
     ///   contain any alphanumeric character, or any of `_-[].`.
     /// - `definition` is the definition of the pattern and is optional. It may
-    ///   contain any character other than `{` or `}`.
+    ///   contain any character, as long as `{` and `}` are balanced or escaped
+    ///   as `\{` and `\}`, eg: `%{CODE=[A-Z]{3}}`.
     ///
     /// A literal `%` character may appear in a grok pattern as long as it is
-    /// not followed by `{`. You can surround the percent with grouped
-    /// parentheses `(%){..}`, a non-capturing group `(?:%){..}`, or use the
-    /// `\x25` escape sequence, ie: `\x25{..}`.
+    /// not followed by `{`, so `%%{NAME}` is a literal `%` followed by a
+    /// pattern. A literal `%{` is written as `\%{`.
+    ///
+    /// If a different pattern of the same name already exists, it is replaced
+    /// and a [`ShadowWarning`] is recorded (see [`Grok::warnings`]). This
+    /// includes patterns of the parent, which are only hidden in this
+    /// instance.
     pub fn add_pattern<S: Into<String>>(&mut self, name: S, pattern: S) {
-        self.patterns
-            .insert(Cow::Owned(name.into()), Cow::Owned(pattern.into()));
+        self.insert_pattern(Cow::Owned(name.into()), Cow::Owned(pattern.into()));
     }
 
-    /// Compiles the given pattern, making it ready for matching.
+    /// Adds a custom grok pattern like [`Grok::add_pattern`], validating it
+    /// first.
     ///
-    /// Specify `with_alias_only` to only include the aliases in the matches
-    /// rather that all named patterns. This may result in a more efficient
-    /// compiled pattern.
-    pub fn compile(&self, pattern: &str, with_alias_only: bool) -> Result<Pattern, Error> {
-        let (named_regex, aliases, extracts) = self.compile_regex(pattern, with_alias_only)?;
-        if named_regex.is_empty() {
-            Err(Error::CompiledPatternIsEmpty(pattern.into()))
-        } else {
-            Pattern::new(&named_regex, aliases, extracts)
+    /// The definition is rejected if one of its placeholders cannot be parsed.
+    /// If `compile` is set, the definition is also compiled with the active
+    /// engine, so that references to missing patterns and invalid regular
+    /// expressions are rejected as well. The pattern is not added if it is
+    /// invalid.
+    ///
+    /// ```
+    /// let mut grok = grok::Grok::default();
+    /// assert!(grok.try_add_pattern("TICKET", "T-%{INT}", true).is_ok());
+    ///
+    /// let error = grok.try_add_pattern("TICKET", "T-%{INT!}", false).unwrap_err();
+    /// assert_eq!(2..9, error.range());
+    /// let error = grok.try_add_pattern("TICKET", "T-%{MISSING}", true).unwrap_err();
+    /// assert_eq!(2..12, error.range());
+    /// ```
+    pub fn try_add_pattern<S: Into<String>>(
+        &mut self,
+        name: S,
+        pattern: S,
+        compile: bool,
+    ) -> Result<(), PatternDefinitionError> {
+        let (name, pattern) = (name.into(), pattern.into());
+        check_syntax(&pattern, 0)?;
+        if compile {
+            let overrides = [(name.as_str(), pattern.as_str())];
+            self.expand_with(&pattern, &overrides, false)
+                .and_then(|expanded| self.build_pattern(expanded))
+                .map_err(|error| {
+                    let range = match &error {
+                        Error::DefinitionNotFound(missing) => {
+                            find_placeholder(&pattern, 0, missing)
+                        }
+                        _ => None,
+                    };
+                    PatternDefinitionError::Compile {
+                        range: range.unwrap_or(0..pattern.len()),
+                        error,
+                    }
+                })?;
         }
+        self.add_pattern(name, pattern);
+        Ok(())
     }
 
-    fn compile_regex(
-        &self,
-        pattern: &str,
-        with_alias_only: bool,
-    ) -> Result<(String, HashMap<String, String>, HashMap<String, String>), Error> {
-        let mut named_regex = String::with_capacity(pattern.len() * 4);
-        let mut aliases: HashMap<String, String> = HashMap::new();
-        let mut aliases_extra: HashMap<String, usize> = HashMap::new();
-        let mut extracts: HashMap<String, String> = HashMap::new();
+    /// Adds a pattern, keeping built-in definitions borrowed so that their
+    /// origin is preserved.
+    fn insert_pattern(&mut self, name: Cow<'static, str>, pattern: Cow<'static, str>) {
+        if self
+            .lookup_local(Scope::Global, &name)
+            .is_some_and(|existing| *existing != pattern)
+        {
+            self.warnings.push(ShadowWarning::Pattern {
+                namespace: None,
+                name: name.to_string(),
+            });
+        }
+        self.cache.invalidate(&name);
+        self.patterns.insert(name, pattern);
+    }
 
-        let mut pattern_stack = Vec::with_capacity(16);
+    /// Removes a global pattern, returning its definition if it existed.
+    ///
+    /// Only the patterns of this instance can be removed: once a pattern that
+    /// hides a pattern of the parent is removed, the pattern of the parent is
+    /// visible again.
+    pub fn remove_pattern(&mut self, name: &str) -> Option<String> {
+        let pattern = self.patterns.remove(name)?;
+        self.cache.invalidate(name);
+        Some(pattern.into_owned())
+    }
 
-        pattern_stack.push((grok_split(pattern), BTreeMap::new()));
-        let mut index = 0;
+    /// Returns the definition of a global pattern.
+    pub fn get_pattern(&self, name: &str) -> Option<&str> {
+        self.lookup_local(Scope::Global, name)
+    }
 
-        while let Some((mut it, pattern_overrides)) = pattern_stack.pop() {
-            if let Some(next) = it.next() {
-                pattern_stack.push((it, pattern_overrides));
-                use GrokComponent::*;
-                match next {
-                    GrokPattern {
-                        name,
-                        alias,
-                        extract,
-                        definition,
-                        ..
-                    } => {
-                        if !definition.is_empty() {
-                            // We can cleverly reborrow the definition here because we know that
-                            // the lifetime is compatible.
-                            pattern_stack
-                                .last_mut()
-                                .unwrap()
-                                .1
-                                .insert(name.to_string(), definition);
-                            pattern_stack.push((grok_split(definition), BTreeMap::new()));
-                        } else if let Some(pattern) = pattern_stack.last().unwrap().1.get(name) {
-                            // Again, cleverly reborrow the pattern
-                            pattern_stack.push((grok_split(*pattern), BTreeMap::new()));
-                        } else {
-                            let Some(pattern) = self.patterns.get(name) else {
-                                return Err(Error::DefinitionNotFound(name.to_string()));
-                            };
-                            pattern_stack.push((grok_split(pattern), BTreeMap::new()));
-                        }
+    /// Returns `true` if a global pattern of the given name exists.
+    pub fn contains_pattern(&self, name: &str) -> bool {
+        self.get_pattern(name).is_some()
+    }
 
-                        if with_alias_only && alias.is_empty() {
-                            named_regex.push_str("(?:");
-                        } else {
-                            let match_name = format!("_n_{index}");
-                            index += 1;
-
-                            let orig_key = if alias.is_empty() { name } else { alias };
-
-                            let count = aliases_extra.entry(orig_key.to_string()).or_insert(0);
-                            let key = if *count == 0 {
-                                orig_key.to_string()
-                            } else {
-                                format!("{orig_key}[{count}]")
-                            };
-                            *count += 1;
-
-                            // This is unlikely but will really mess things up if it happens.
-                            if *count > 1 && aliases_extra.contains_key(&key) {
-                                return Err(Error::GenericCompilationFailure(format!(
-                                    "Alias {key} already exists"
-                                )));
-                            }
+    /// Returns whether a global pattern is built-in or was added by the user,
+    /// or `None` if it does not exist.
+    ///
+    /// ```
+    /// use grok::{Grok, PatternOrigin};
+    ///
+    /// let mut grok = Grok::default();
+    /// grok.add_pattern("TICKET", "T-%{INT}");
+    /// assert_eq!(Some(PatternOrigin::Builtin), grok.pattern_origin("INT"));
+    /// assert_eq!(Some(PatternOrigin::User), grok.pattern_origin("TICKET"));
+    /// ```
+    pub fn pattern_origin(&self, name: &str) -> Option<PatternOrigin> {
+        // Built-in definitions are the only borrowed ones.
+        self.lookup_global(name).map(|pattern| match pattern {
+            Cow::Borrowed(_) => PatternOrigin::Builtin,
+            Cow::Owned(_) => PatternOrigin::User,
+        })
+    }
 
-                            if !extract.is_empty() {
-                                extracts.insert(key.clone(), extract.to_string());
-                            }
-                            aliases.insert(match_name.clone(), key);
+    /// Returns the names and definitions of the global patterns, including
+    /// those of the parent, sorted by name.
+    pub fn iter_patterns(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.local_names(Scope::Global).into_iter().map(|name| {
+            let pattern = self.lookup_global(name).expect("pattern exists");
+            (name, pattern.as_ref())
+        })
+    }
 
-                            named_regex.push_str("(?<");
-                            named_regex.push_str(&match_name);
-                            named_regex.push('>');
-                        }
-                    }
-                    RegularExpression { string, .. } => {
-                        named_regex.push_str(string);
-                    }
-                    PatternError(e) => {
-                        return Err(Error::GenericCompilationFailure(format!("{e:?}")));
-                    }
+    /// Adds the global patterns and the namespaces of another instance to this
+    /// one, as if they were added one by one with [`Grok::add_pattern`],
+    /// [`Grok::add_namespace_pattern`] and [`Grok::import_namespace`].
+    ///
+    /// Built-in patterns keep their [`PatternOrigin`].
+    pub fn extend(&mut self, other: &Grok) {
+        for name in other.local_names(Scope::Global) {
+            let pattern = other.lookup_global(name).expect("pattern exists");
+            self.insert_pattern(Cow::Owned(name.to_string()), pattern.clone());
+        }
+        let namespaces = other
+            .layers()
+            .flat_map(|grok| grok.namespaces.keys())
+            .collect::<BTreeSet<_>>();
+        for namespace in namespaces {
+            for import in other.namespace_imports(namespace) {
+                self.import_namespace(namespace.as_str(), import);
+            }
+            let scope = Scope::Namespace(namespace);
+            for name in other.local_names(scope) {
+                let pattern = other.lookup_local(scope, name).expect("pattern exists");
+                self.add_namespace_pattern(namespace.as_str(), name, pattern);
+            }
+        }
+    }
+
+    /// Adds a custom grok pattern to a namespace, creating the namespace if it
+    /// does not exist.
+    ///
+    /// The patterns of a namespace are referred to as `%{namespace::NAME}`, so
+    /// independent pattern libraries cannot collide with each other or with
+    /// the global patterns. Every built-in [`PatternGroup`] of the pattern set
+    /// is also available as a namespace, eg: `%{httpd::HTTPD_COMBINEDLOG}`,
+    /// which is unaffected by global patterns that replace its dependencies.
+    ///
+    /// Unqualified references inside the patterns of a namespace are looked up
+    /// in the namespace first, then in its imports (see
+    /// [`Grok::import_namespace`]), and then in the global patterns.
+    ///
+    /// Note that `%{NAME::extract}` also denotes a pattern with an empty alias
+    /// and an extract, which is how it is read when `NAME` is not a namespace.
+    ///
+    /// ```
+    /// let mut grok = grok::Grok::empty();
+    /// grok.import_namespace("acme", "grok");
+    /// grok.add_namespace_pattern("acme", "REQUEST", "%{WORD:verb} %{URIPATH:path}");
+    /// let pattern = grok.compile("%{acme::REQUEST}", true).unwrap();
+    /// let matches = pattern.match_against("GET /index.html").unwrap();
+    /// assert_eq!(Some("GET"), matches.get("verb"));
+    /// ```
+    pub fn add_namespace_pattern<S: Into<String>>(&mut self, namespace: S, name: S, pattern: S) {
+        let (namespace, name, pattern) = (namespace.into(), name.into(), pattern.into());
+        self.ensure_namespace(&namespace);
+
+        let mut warnings = vec![];
+        if self
+            .lookup_local(Scope::Namespace(&namespace), &name)
+            .is_some_and(|existing| *existing != pattern)
+        {
+            warnings.push(ShadowWarning::Pattern {
+                namespace: Some(namespace.clone()),
+                name: name.clone(),
+            });
+        }
+        for import in self.namespace_imports(&namespace) {
+            if let Some(scope) = self.namespace_scope(import) {
+                if self.lookup_local(scope, &name).is_some() {
+                    warnings.push(ShadowWarning::Import {
+                        namespace: namespace.clone(),
+                        import: import.to_string(),
+                        name: name.clone(),
+                    });
                 }
-            } else {
-                named_regex.push(')');
             }
+        }
+        self.warnings.extend(warnings);
+
+        self.cache.invalidate(&name);
+        self.namespaces
+            .get_mut(&namespace)
+            .unwrap()
+            .patterns
+            .insert(name, pattern);
+    }
 
-            if pattern_stack.len() > MAX_RECURSION {
-                return Err(Error::RecursionTooDeep);
+    /// Makes the patterns of the namespace `import` available to the patterns
+    /// of `namespace` without qualification, creating `namespace` if it does
+    /// not exist.
+    ///
+    /// `import` may be a namespace added with [`Grok::add_namespace_pattern`]
+    /// or the namespace of a built-in [`PatternGroup`]. Imports are looked up
+    /// in the order they were added, after the patterns of the namespace
+    /// itself, and are not transitive. A pattern that is hidden this way is
+    /// reported as a [`ShadowWarning`] if `import` is known at this point.
+    pub fn import_namespace<S: Into<String>>(&mut self, namespace: S, import: S) {
+        let (namespace, import) = (namespace.into(), import.into());
+        self.ensure_namespace(&namespace);
+
+        let imports = self.namespace_imports(&namespace);
+        if imports.contains(&import.as_str()) {
+            return;
+        }
+        let mut warnings = vec![];
+        if let Some(scope) = self.namespace_scope(&import) {
+            let earlier = imports
+                .iter()
+                .filter_map(|import| self.namespace_scope(import))
+                .collect::<Vec<_>>();
+            for name in self.local_names(scope) {
+                if self
+                    .lookup_local(Scope::Namespace(&namespace), name)
+                    .is_some()
+                    || earlier
+                        .iter()
+                        .any(|scope| self.lookup_local(*scope, name).is_some())
+                {
+                    warnings.push(ShadowWarning::Import {
+                        namespace: namespace.clone(),
+                        import: import.clone(),
+                        name: name.to_string(),
+                    });
+                }
             }
         }
+        self.warnings.extend(warnings);
+
+        self.cache.clear();
+        self.namespaces
+            .get_mut(&namespace)
+            .unwrap()
+            .imports
+            .push(import);
+    }
 
-        named_regex.pop();
-        Ok((named_regex, aliases, extracts))
+    /// Returns the warnings about definitions that hide other definitions,
+    /// in the order they were added.
+    pub fn warnings(&self) -> &[ShadowWarning] {
+        &self.warnings
     }
-}
+
+    /// Clears the warnings returned by [`Grok::warnings`].
+    pub fn clear_warnings(&mut self) {
+        self.warnings.clear();
+    }
+
+    fn ensure_namespace(&mut self, namespace: &str) {
+        if self.namespaces.contains_key(namespace) {
+            return;
+        }
+        if !self
+            .layers()
+            .any(|grok| grok.namespaces.contains_key(namespace))
+            && self.namespace_scope(namespace).is_some()
+        {
+            self.warnings.push(ShadowWarning::Namespace {
+                namespace: namespace.to_string(),
+            });
+        }
+        self.cache.clear();
+        self.namespaces
+            .insert(namespace.to_string(), Namespace::default());
+    }
+
+    /// Returns this instance followed by its ancestors.
+    fn layers(&self) -> impl Iterator<Item = &Grok> {
+        std::iter::successors(Some(self), |grok| grok.parent.as_deref())
+    }
+
+    /// Returns the imports of a namespace, those of the ancestors first.
+    fn namespace_imports(&self, namespace: &str) -> Vec<&str> {
+        let mut imports = Vec::new();
+        let layers = self.layers().collect::<Vec<_>>();
+        for layer in layers.into_iter().rev() {
+            let Some(ns) = layer.namespaces.get(namespace) else {
+                continue;
+            };
+            for import in &ns.imports {
+                if !imports.contains(&import.as_str()) {
+                    imports.push(import.as_str());
+                }
+            }
+        }
+        imports
+    }
+
+    /// Returns the scope of the given namespace, if it exists.
+    fn namespace_scope(&self, namespace: &str) -> Option<Scope<'_>> {
+        if let Some((namespace, _)) = self
+            .layers()
+            .find_map(|grok| grok.namespaces.get_key_value(namespace))
+        {
+            return Some(Scope::Namespace(namespace));
+        }
+        PatternGroup::from_namespace(namespace)
+            .filter(|group| !group.patterns_cow(self.set).is_empty())
+            .map(Scope::Builtin)
+    }
+
+    /// Returns the names of the patterns defined in a scope, not including its
+    /// imports.
+    fn local_names(&self, scope: Scope<'_>) -> Vec<&str> {
+        let names = match scope {
+            Scope::Global => self
+                .layers()
+                .flat_map(|grok| grok.patterns.keys().map(|name| name.as_ref()))
+                .collect::<BTreeSet<_>>(),
+            Scope::Builtin(group) => group.patterns(self.set).map(|(name, _)| name).collect(),
+            Scope::Namespace(namespace) => self
+                .layers()
+                .filter_map(|grok| grok.namespaces.get(namespace))
+                .flat_map(|ns| ns.patterns.keys().map(String::as_str))
+                .collect(),
+        };
+        names.into_iter().collect()
+    }
+
+    /// Looks up a global pattern, keeping its origin.
+    fn lookup_global(&self, name: &str) -> Option<&Cow<'static, str>> {
+        self.layers().find_map(|grok| grok.patterns.get(name))
+    }
+
+    /// Looks up a pattern defined in a scope, not including its imports.
+    fn lookup_local(&self, scope: Scope<'_>, name: &str) -> Option<&str> {
+        match scope {
+            Scope::Global => self.lookup_global(name).map(|pattern| pattern.as_ref()),
+            Scope::Builtin(group) => group.definition(self.set, name),
+            Scope::Namespace(namespace) => self
+                .layers()
+                .find_map(|grok| grok.namespaces.get(namespace)?.patterns.get(name))
+                .map(String::as_str),
+        }
+    }
+
+    /// Splits the name of a reference into its namespace, if any, and the name
+    /// of the pattern, along with the actual extract. Returns `None` if the
+    /// name is qualified by a namespace that does not exist.
+    fn split_reference<'p>(
+        &self,
+        name: &'p str,
+        alias: &str,
+        extract: &'p str,
+    ) -> Option<(Option<Scope<'_>>, &'p str, &'p str)> {
+        match name.split_once("::") {
+            Some((namespace, name)) => match self.namespace_scope(namespace) {
+                Some(namespace) => Some((Some(namespace), name, extract)),
+                // `%{NAME::extract}` has an empty alias and an extract
+                None if alias.is_empty() && extract.is_empty() => Some((None, namespace, name)),
+                None => None,
+            },
+            None => Some((None, name, extract)),
+        }
+    }
+
+    /// Returns whether the definition of a pattern found in a scope is one of
+    /// the built-in definitions.
+    fn is_builtin(&self, scope: Scope<'_>, name: &str) -> bool {
+        match scope {
+            Scope::Global => matches!(self.lookup_global(name), Some(Cow::Borrowed(_))),
+            Scope::Builtin(_) => true,
+            Scope::Namespace(_) => false,
+        }
+    }
+
+    /// Looks up a pattern referred to from a scope, returning its definition
+    /// and the scope it is defined in.
+    fn resolve<'a>(&'a self, scope: Scope<'a>, name: &str) -> Option<(&'a str, Scope<'a>)> {
+        if let Some(pattern) = self.lookup_local(scope, name) {
+            return Some((pattern, scope));
+        }
+        let imports = match scope {
+            Scope::Global => return None,
+            Scope::Builtin(group) => group
+                .dependencies()
+                .iter()
+                .map(|group| Scope::Builtin(*group))
+                .collect::<Vec<_>>(),
+            Scope::Namespace(namespace) => self
+                .namespace_imports(namespace)
+                .into_iter()
+                .filter_map(|import| self.namespace_scope(import))
+                .collect(),
+        };
+        for import in imports {
+            if let Some(pattern) = self.lookup_local(import, name) {
+                return Some((pattern, import));
+            }
+        }
+        self.resolve(Scope::Global, name)
+    }
+
+    /// Compiles the given pattern, making it ready for matching.
+    ///
+    /// Specify `with_alias_only` to only include the aliases in the matches
+    /// rather that all named patterns. This may result in a more efficient
+    /// compiled pattern.
+    pub fn compile(&self, pattern: &str, with_alias_only: bool) -> Result<Pattern, Error> {
+        let expanded = self.expand(pattern, with_alias_only)?;
+        if expanded.regex.is_empty() {
+            Err(Error::CompiledPatternIsEmpty(pattern.into()))
+        } else {
+            self.build_pattern(expanded)
+        }
+    }
+
+    /// Compiles patterns with the given engine rather than the engine selected
+    /// by the crate features. The engine is inherited by the instances
+    /// layered on this one with [`Grok::with_parent`].
+    ///
+    /// The built-in patterns are loaded for the engine selected by the crate
+    /// features, and translated to the dialect of the given engine when they
+    /// are compiled.
+    pub fn set_engine<E: GrokEngine + 'static>(&mut self, engine: E) {
+        self.custom_engine = Some(Arc::new(engine));
+        self.cache.clear();
+    }
+
+    /// Enables or disables capture history, which records every iteration of
+    /// the captures inside repeated groups for [`Matches::history`].
+    ///
+    /// With the built-in Oniguruma engine, the history is recorded by
+    /// Oniguruma when the repeated captures are among the first 31 groups of
+    /// the pattern. Otherwise, it is emulated by matching the iterations of a
+    /// repeated group one by one, each on its own, so anchors, word
+    /// boundaries, look-around and backreferences that reach outside of an
+    /// iteration do not see the rest of the text, and the iteration may be
+    /// missing from the history. Retrieving the history matches the text
+    /// again, and is disabled by default.
+    pub fn set_capture_history(&mut self, enabled: bool) {
+        self.capture_history = enabled;
+        self.cache.clear();
+    }
+
+    /// Returns whether capture history is enabled.
+    pub fn capture_history(&self) -> bool {
+        self.capture_history
+    }
+
+    /// Compiles an expanded pattern with the engine of this instance.
+    fn build_pattern(&self, expanded: ExpandedPattern) -> Result<Pattern, Error> {
+        let engine = self.custom_engine.as_deref();
+        if !self.capture_history {
+            return Pattern::new(expanded, engine);
+        }
+        let aliases = expanded.aliases.clone();
+        let mut pattern = Pattern::new(expanded, engine)?;
+        // The regex is already translated to the dialect of the engine
+        pattern.history = CaptureHistory::new(&pattern.regex, &aliases, engine)?.map(Box::new);
+        Ok(pattern)
+    }
+
+    /// Sets the maximum depth of nested patterns while compiling, beyond which
+    /// [`Error::RecursionTooDeep`] is returned. The default is 1024.
+    ///
+    /// Patterns that refer to themselves are reported as
+    /// [`Error::CyclicDefinition`] regardless of the limit.
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+        self.cache.clear();
+    }
+
+    /// Returns the maximum depth of nested patterns while compiling.
+    pub fn recursion_limit(&self) -> usize {
+        self.recursion_limit
+    }
+
+    /// Compiles the given pattern like [`Grok::compile`], sharing the result
+    /// through the cache enabled with [`Grok::set_cache_capacity`].
+    ///
+    /// A cached pattern is returned for as long as none of the patterns it
+    /// was expanded from are replaced. Without a cache, this compiles the
+    /// pattern every time.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// let mut grok = grok::Grok::default();
+    /// grok.set_cache_capacity(16);
+    /// let first = grok.compile_cached("%{USERNAME}", true).unwrap();
+    /// let second = grok.compile_cached("%{USERNAME}", true).unwrap();
+    /// assert!(Arc::ptr_eq(&first, &second));
+    ///
+    /// grok.add_pattern("USERNAME", "[a-z]+");
+    /// let third = grok.compile_cached("%{USERNAME}", true).unwrap();
+    /// assert!(!Arc::ptr_eq(&first, &third));
+    /// ```
+    pub fn compile_cached(
+        &self,
+        pattern: &str,
+        with_alias_only: bool,
+    ) -> Result<Arc<Pattern>, Error> {
+        if let Some(compiled) = self.cache.get(pattern, with_alias_only) {
+            return Ok(compiled);
+        }
+        let mut expanded = self.expand(pattern, with_alias_only)?;
+        if expanded.regex.is_empty() {
+            return Err(Error::CompiledPatternIsEmpty(pattern.into()));
+        }
+        let dependencies = std::mem::take(&mut expanded.dependencies);
+        let compiled = Arc::new(self.build_pattern(expanded)?);
+        self.cache
+            .insert(pattern, with_alias_only, compiled.clone(), dependencies);
+        Ok(compiled)
+    }
+
+    /// Sets the maximum number of patterns kept by [`Grok::compile_cached`],
+    /// dropping the least recently used ones beyond it. The cache is disabled
+    /// with a capacity of zero, which is the default.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.set_capacity(capacity);
+    }
+
+    /// Returns the capacity of the pattern cache.
+    pub fn cache_capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    /// Returns the number of patterns in the pattern cache.
+    pub fn cache_len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Drops every pattern from the pattern cache.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Expands the given pattern into a plain regular expression without
+    /// compiling it.
+    ///
+    /// This is useful to debug expansions or to use the regular expression
+    /// with other tools. `with_alias_only` has the same meaning as in
+    /// [`Grok::compile`].
+    pub fn expand(&self, pattern: &str, with_alias_only: bool) -> Result<ExpandedPattern, Error> {
+        self.compile_regex(pattern, &[], with_alias_only)
+    }
+
+    /// Compiles the given pattern with some named patterns replaced for this
+    /// call only.
+    ///
+    /// Each override replaces every reference to its name throughout the
+    /// expansion, including references made from built-in namespaces, unless
+    /// a `%{NAME=definition}` in the pattern itself takes precedence. A
+    /// namespaced reference is only replaced by an override of its full
+    /// `namespace::name`. Overrides are resolved like global patterns.
+    ///
+    /// ```
+    /// use grok::Grok;
+    ///
+    /// # #[cfg(any(not(feature = "select-patterns"), feature = "patterns-haproxy"))] {
+    /// let grok = Grok::default();
+    /// let pattern = grok
+    ///     .compile_with(
+    ///         "%{HAPROXYHTTP}",
+    ///         &[("HAPROXYCAPTUREDREQUESTHEADERS", "%{DATA:host}")],
+    ///         true,
+    ///     )
+    ///     .expect("valid pattern");
+    /// assert!(pattern.capture_names().any(|name| name == "host"));
+    /// # }
+    /// ```
+    pub fn compile_with(
+        &self,
+        pattern: &str,
+        overrides: &[(&str, &str)],
+        with_alias_only: bool,
+    ) -> Result<Pattern, Error> {
+        let expanded = self.expand_with(pattern, overrides, with_alias_only)?;
+        if expanded.regex.is_empty() {
+            Err(Error::CompiledPatternIsEmpty(pattern.into()))
+        } else {
+            self.build_pattern(expanded)
+        }
+    }
+
+    /// Expands the given pattern with some named patterns replaced, as
+    /// described in [`Grok::compile_with`], without compiling it.
+    pub fn expand_with(
+        &self,
+        pattern: &str,
+        overrides: &[(&str, &str)],
+        with_alias_only: bool,
+    ) -> Result<ExpandedPattern, Error> {
+        self.compile_regex(pattern, overrides, with_alias_only)
+    }
+
+    /// Parses the given pattern into an [`Ast`](parser::Ast), resolving every
+    /// placeholder to the definition it refers to.
+    ///
+    /// References are resolved like [`Grok::compile`] does, so the same
+    /// [`Error::DefinitionNotFound`] and [`Error::CyclicDefinition`] errors are
+    /// returned. Placeholders with an inline definition are not resolved.
+    ///
+    /// ```
+    /// use grok::parser::Node;
+    ///
+    /// let grok = grok::Grok::default();
+    /// let ast = grok.parse("%{INT:number}").unwrap();
+    /// let Node::Placeholder(int) = &ast.nodes[0] else { unreachable!() };
+    /// assert_eq!(
+    ///     "(?:[+-]?(?:[0-9]+))",
+    ///     int.resolved.as_ref().unwrap().to_string()
+    /// );
+    /// ```
+    pub fn parse<'a>(&'a self, pattern: &'a str) -> Result<parser::Ast<'a>, Error> {
+        let mut ast =
+            ast::parse(pattern).map_err(|e| Error::GenericCompilationFailure(e.to_string()))?;
+        self.resolve_placeholders(&mut ast, Scope::Global, &mut vec![None])?;
+        Ok(ast)
+    }
+
+    /// Resolves the placeholders of a parsed pattern defined in `scope`.
+    /// `stack` holds the names of the patterns being resolved, or `None` for
+    /// the pattern itself and for inline definitions.
+    fn resolve_placeholders<'a>(
+        &'a self,
+        ast: &mut parser::Ast<'a>,
+        scope: Scope<'a>,
+        stack: &mut Vec<Option<String>>,
+    ) -> Result<(), Error> {
+        let mut definitions = BTreeMap::new();
+        ast.for_each_placeholder_mut(&mut |placeholder| {
+            let reference = Reference {
+                name: &placeholder.name,
+                alias: &placeholder.alias,
+                extract: &placeholder.extract,
+                has_definition: placeholder.definition.is_some(),
+            };
+            let (name, _, target) = self
+                .resolve_reference(scope, reference, &[], |name| definitions.contains_key(name))?;
+            let (pattern, scope, key) = match target {
+                Target::Definition => {
+                    let definition = placeholder.definition.as_mut().unwrap();
+                    self.check_expansion(stack.iter().map(Option::as_ref), None)?;
+                    stack.push(None);
+                    self.resolve_placeholders(definition, scope, stack)?;
+                    stack.pop();
+                    definitions.insert(name.to_string(), definition.clone());
+                    return Ok(());
+                }
+                Target::Inline => {
+                    placeholder.resolved = Some(Box::new(definitions[name].clone()));
+                    return Ok(());
+                }
+                Target::Named {
+                    pattern,
+                    scope,
+                    key,
+                    ..
+                } => (pattern, scope, key),
+            };
+
+            self.check_expansion(stack.iter().map(Option::as_ref), Some(&key))?;
+            let mut resolved = ast::parse(pattern)
+                .map_err(|e| Error::GenericCompilationFailure(format!("{key}: {e}")))?;
+            stack.push(Some(key));
+            self.resolve_placeholders(&mut resolved, scope, stack)?;
+            stack.pop();
+            placeholder.resolved = Some(Box::new(resolved));
+            Ok(())
+        })
+    }
+
+    /// Finds what a placeholder found in a pattern of `scope` refers to, and
+    /// returns it along with the name of the pattern, without its namespace,
+    /// and the actual extract. `is_inline` tells whether a name has an inline
+    /// definition earlier in the same pattern.
+    fn resolve_reference<'a, 'r>(
+        &'a self,
+        scope: Scope<'a>,
+        reference: Reference<'r>,
+        overrides: &[(&'a str, &'a str)],
+        is_inline: impl Fn(&str) -> bool,
+    ) -> Result<(&'r str, &'r str, Target<'a>), Error> {
+        let find_override = |name: &str| {
+            overrides
+                .iter()
+                .rev()
+                .find(|(key, _)| *key == name)
+                .map(|(_, pattern)| *pattern)
+        };
+        let Reference {
+            name: qualified_name,
+            alias,
+            extract,
+            has_definition,
+        } = reference;
+        let Some((namespace, name, extract)) = self.split_reference(qualified_name, alias, extract)
+        else {
+            return Err(Error::DefinitionNotFound(qualified_name.to_string()));
+        };
+
+        let target = if has_definition {
+            Target::Definition
+        } else if let Some(pattern) = namespace.and(find_override(qualified_name)) {
+            Target::Named {
+                pattern,
+                scope: Scope::Global,
+                key: qualified_name.to_string(),
+                is_override: true,
+                is_builtin: false,
+            }
+        } else if let Some(namespace) = namespace {
+            let Some(pattern) = self.lookup_local(namespace, name) else {
+                return Err(Error::DefinitionNotFound(qualified_name.to_string()));
+            };
+            Target::Named {
+                pattern,
+                scope: namespace,
+                key: namespace.qualify(name),
+                is_override: false,
+                is_builtin: self.is_builtin(namespace, name),
+            }
+        } else if is_inline(name) {
+            Target::Inline
+        } else if let Some(pattern) = find_override(name) {
+            Target::Named {
+                pattern,
+                scope: Scope::Global,
+                key: name.to_string(),
+                is_override: true,
+                is_builtin: false,
+            }
+        } else {
+            let Some((pattern, scope)) = self.resolve(scope, name) else {
+                return Err(Error::DefinitionNotFound(name.to_string()));
+            };
+            Target::Named {
+                pattern,
+                scope,
+                key: scope.qualify(name),
+                is_override: false,
+                is_builtin: self.is_builtin(scope, name),
+            }
+        };
+        Ok((name, extract, target))
+    }
+
+    /// Checks that expanding the pattern known as `key`, or an inline
+    /// definition if `None`, within the patterns being expanded, given
+    /// outermost first by their keys, neither forms a cycle nor goes deeper
+    /// than the recursion limit.
+    fn check_expansion<'k>(
+        &self,
+        expanding: impl ExactSizeIterator<Item = Option<&'k String>>,
+        key: Option<&String>,
+    ) -> Result<(), Error> {
+        let depth = expanding.len();
+        if let Some(key) = key {
+            let mut cycle = expanding
+                .flatten()
+                .skip_while(|expanding| *expanding != key)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !cycle.is_empty() {
+                cycle.push(key.clone());
+                return Err(Error::CyclicDefinition(cycle));
+            }
+        }
+        if depth >= self.recursion_limit {
+            return Err(Error::RecursionTooDeep(self.recursion_limit));
+        }
+        Ok(())
+    }
+
+    fn compile_regex<'a>(
+        &'a self,
+        pattern: &'a str,
+        overrides: &[(&'a str, &'a str)],
+        with_alias_only: bool,
+    ) -> Result<ExpandedPattern, Error> {
+        let mut named_regex = String::with_capacity(pattern.len() * 4);
+        let mut aliases: HashMap<String, String> = HashMap::new();
+        let mut aliases_extra: HashMap<String, usize> = HashMap::new();
+        let mut extracts: HashMap<String, String> = HashMap::new();
+        let mut dependencies = BTreeSet::new();
+        let mut captures: Vec<Capture> = vec![];
+        let mut builtin_ranges: Vec<Range<usize>> = vec![];
+
+        let mut pattern_stack = Vec::with_capacity(16);
+
+        pattern_stack.push(Frame {
+            components: grok_split(pattern),
+            definitions: BTreeMap::new(),
+            scope: Scope::Global,
+            key: None,
+            capture: None,
+            is_builtin: false,
+        });
+        let mut index = 0;
+
+        while let Some(frame) = pattern_stack.last_mut() {
+            let Some(next) = frame.components.next() else {
+                pattern_stack.pop();
+                named_regex.push(')');
+                continue;
+            };
+            let (scope, is_builtin) = (frame.scope, frame.is_builtin);
+            use GrokComponent::*;
+            match next {
+                GrokPattern {
+                    name: qualified_name,
+                    alias,
+                    extract,
+                    definition,
+                    ..
+                } => {
+                    let reference = Reference {
+                        name: qualified_name,
+                        alias,
+                        extract,
+                        has_definition: !definition.is_empty(),
+                    };
+                    let (name, extract, target) =
+                        self.resolve_reference(scope, reference, overrides, |name| {
+                            frame.definitions.contains_key(name)
+                        })?;
+
+                    // The pattern to expand, its scope, the name it is known by if it
+                    // may take part in a cycle, and whether it is a built-in definition
+                    let (pattern, scope, key, is_builtin) = match target {
+                        Target::Definition => {
+                            frame.definitions.insert(name, definition);
+                            (definition, scope, None, is_builtin)
+                        }
+                        Target::Inline => (frame.definitions[name], scope, None, is_builtin),
+                        Target::Named {
+                            pattern,
+                            scope,
+                            key,
+                            is_override,
+                            is_builtin,
+                        } => {
+                            if !is_override {
+                                dependencies.insert(key.clone());
+                            }
+                            (pattern, scope, Some(key), is_builtin)
+                        }
+                    };
+
+                    self.check_expansion(
+                        pattern_stack.iter().map(|frame| frame.key.as_ref()),
+                        key.as_ref(),
+                    )?;
+                    // The placeholder this one was found in
+                    let parent = pattern_stack.last().unwrap().capture;
+                    pattern_stack.push(Frame {
+                        components: grok_split(pattern),
+                        definitions: BTreeMap::new(),
+                        scope,
+                        key,
+                        capture: Some(captures.len()),
+                        is_builtin,
+                    });
+                    captures.push(Capture {
+                        pattern: qualified_name.to_string(),
+                        alias: alias.to_string(),
+                        key: None,
+                        parent,
+                    });
+
+                    if with_alias_only && alias.is_empty() {
+                        named_regex.push_str("(?:");
+                    } else {
+                        let match_name = format!("_n_{index}");
+                        index += 1;
+
+                        let orig_key = if alias.is_empty() { name } else { alias };
+
+                        let count = aliases_extra.entry(orig_key.to_string()).or_insert(0);
+                        let key = if *count == 0 {
+                            orig_key.to_string()
+                        } else {
+                            format!("{orig_key}[{count}]")
+                        };
+                        *count += 1;
+
+                        // This is unlikely but will really mess things up if it happens.
+                        if *count > 1 && aliases_extra.contains_key(&key) {
+                            return Err(Error::GenericCompilationFailure(format!(
+                                "Alias {key} already exists"
+                            )));
+                        }
+
+                        if !extract.is_empty() {
+                            extracts.insert(key.clone(), extract.to_string());
+                        }
+                        captures.last_mut().unwrap().key = Some(key.clone());
+                        aliases.insert(match_name.clone(), key);
+
+                        named_regex.push_str("(?<");
+                        named_regex.push_str(&match_name);
+                        named_regex.push('>');
+                    }
+                }
+                RegularExpression { string, .. } => {
+                    let start = named_regex.len();
+                    named_regex.push_str(string);
+                    if is_builtin {
+                        match builtin_ranges.last_mut() {
+                            Some(range) if range.end == start => range.end = named_regex.len(),
+                            _ => builtin_ranges.push(start..named_regex.len()),
+                        }
+                    }
+                }
+                Escape { .. } => {
+                    // A literal `%{`, escaped for every engine
+                    named_regex.push_str(r"%\{");
+                }
+                PatternError(e) => {
+                    return Err(Error::GenericCompilationFailure(format!("{e:?}")));
+                }
+            }
+        }
+
+        named_regex.pop();
+        Ok(ExpandedPattern {
+            regex: named_regex,
+            aliases,
+            extracts,
+            dependencies,
+            captures,
+            builtin: builtin_ranges,
+        })
+    }
+}
+
+/// Checks that every placeholder of a pattern, including those of inline
+/// definitions, can be parsed. `offset` is the position of `pattern` in the
+/// definition being checked.
+fn check_syntax(pattern: &str, offset: usize) -> Result<(), PatternDefinitionError> {
+    let mut end = 0;
+    for component in grok_split(pattern) {
+        match component {
+            GrokComponent::RegularExpression { range, .. }
+            | GrokComponent::Escape { range, .. } => end = range.end,
+            GrokComponent::GrokPattern {
+                range, definition, ..
+            } => {
+                if !definition.is_empty() {
+                    check_syntax(definition, offset + definition_start(&range, definition))?;
+                }
+                end = range.end;
+            }
+            GrokComponent::PatternError(error) => {
+                // The placeholder that failed to parse starts where the last
+                // component ended, and extends to its closing brace.
+                let length = pattern[end..]
+                    .find('}')
+                    .map_or(pattern.len() - end, |index| index + 1);
+                return Err(PatternDefinitionError::Parse {
+                    range: offset + end..offset + end + length,
+                    error: error.offset(offset),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the range of the first placeholder that refers to `name`, looking
+/// into inline definitions as well.
+fn find_placeholder(pattern: &str, offset: usize, name: &str) -> Option<Range<usize>> {
+    grok_split(pattern).find_map(|component| match component {
+        GrokComponent::GrokPattern {
+            range,
+            name: placeholder,
+            definition,
+            ..
+        } => {
+            if placeholder == name {
+                Some(offset + range.start..offset + range.end)
+            } else if definition.is_empty() {
+                None
+            } else {
+                let start = offset + definition_start(&range, definition);
+                find_placeholder(definition, start, name)
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Returns where the inline definition of the placeholder spanning `range`
+/// starts: it ends right before the closing brace.
+fn definition_start(range: &Range<usize>, definition: &str) -> usize {
+    range.end - 1 - definition.len()
+}
 
 /// The Default implementation for Grok whuich will load the default patterns.
 impl Default for Grok {
@@ -444,14 +2092,21 @@ impl<S: Into<String>, const N: usize> From<[(S, S); N]> for Grok {
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum Error {
-    /// The recursion while compiling has exhausted the limit.
-    RecursionTooDeep,
+    /// The recursion while compiling has exhausted the given limit, see
+    /// [`Grok::set_recursion_limit`].
+    RecursionTooDeep(usize),
+    /// A pattern refers to itself, directly or through other patterns. The
+    /// patterns of the cycle are listed in expansion order, starting and
+    /// ending with the same pattern.
+    CyclicDefinition(Vec<String>),
     /// After compiling, the resulting compiled regex pattern is empty.
     CompiledPatternIsEmpty(String),
     /// A corresponding pattern definition could not be found for the given name.
     DefinitionNotFound(String),
     /// If the compilation for a specific regex in the underlying engine failed.
     RegexCompilationFailed(String),
+    /// The regex uses constructs that the underlying engine does not support.
+    RegexTranslationFailed(String),
     /// Something is messed up during the compilation phase.
     GenericCompilationFailure(String),
 }
@@ -459,10 +2114,12 @@ pub enum Error {
 impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::RecursionTooDeep => "compilation recursion reached the limit",
+            Error::RecursionTooDeep(_) => "compilation recursion reached the limit",
+            Error::CyclicDefinition(_) => "pattern definition refers to itself",
             Error::CompiledPatternIsEmpty(_) => "compiled pattern is empty",
             Error::DefinitionNotFound(_) => "pattern definition not found while compiling",
             Error::RegexCompilationFailed(_) => "regex compilation in the engine failed",
+            Error::RegexTranslationFailed(_) => "regex is not supported by the engine",
             Error::GenericCompilationFailure(_) => {
                 "something happened during the compilation phase"
             }
@@ -477,10 +2134,13 @@ impl StdError for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::RecursionTooDeep => write!(
+            Error::RecursionTooDeep(limit) => {
+                write!(f, "Recursion while compiling reached the limit of {limit}")
+            }
+            Error::CyclicDefinition(ref cycle) => write!(
                 f,
-                "Recursion while compiling reached the limit of {}",
-                MAX_RECURSION
+                "The given pattern definitions form a cycle: {}",
+                cycle.join(" -> ")
             ),
             Error::CompiledPatternIsEmpty(ref p) => write!(
                 f,
@@ -497,6 +2157,11 @@ impl fmt::Display for Error {
                 "The given regex \"{}\" failed compilation in the underlying engine",
                 r
             ),
+            Error::RegexTranslationFailed(ref r) => write!(
+                f,
+                "The given regex could not be translated for the underlying engine: {}",
+                r
+            ),
             Error::GenericCompilationFailure(ref d) => write!(
                 f,
                 "Something unexpected happened during the compilation phase: \"{}\"",
@@ -506,10 +2171,68 @@ impl fmt::Display for Error {
     }
 }
 
+/// An error returned by [`Grok::try_add_pattern`] for an invalid definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatternDefinitionError {
+    /// A placeholder of the definition could not be parsed.
+    Parse {
+        /// The byte range of the placeholder in the definition.
+        range: Range<usize>,
+        /// The parse error, with a range within the definition.
+        error: GrokPatternError,
+    },
+    /// The definition could not be compiled with the active engine.
+    Compile {
+        /// The byte range of the missing reference in the definition, or the
+        /// whole definition.
+        range: Range<usize>,
+        /// The compilation error.
+        error: Error,
+    },
+}
+
+impl PatternDefinitionError {
+    /// Returns the byte range of the invalid part of the definition.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            PatternDefinitionError::Parse { range, .. }
+            | PatternDefinitionError::Compile { range, .. } => range.clone(),
+        }
+    }
+}
+
+impl StdError for PatternDefinitionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PatternDefinitionError::Parse { .. } => None,
+            PatternDefinitionError::Compile { error, .. } => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for PatternDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternDefinitionError::Parse { range, error } => {
+                write!(f, "The pattern definition has an {} at {:?}", error, range)
+            }
+            PatternDefinitionError::Compile { range, error } => {
+                write!(
+                    f,
+                    "The pattern definition at {:?} failed to compile: {}",
+                    range, error
+                )
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::pattern_parser::ExpectedToken;
 
     #[test]
     fn test_simple_anonymous_pattern() {
@@ -754,9 +2477,6 @@ mod tests {
 
     #[test]
     fn test_loaded_default_patterns() {
-        if ENGINE == Engine::Regex {
-            return;
-        }
         let grok = Grok::with_default_patterns();
         let pattern = grok
             .compile("%{DAY} %{MONTH} %{YEAR}", false)
@@ -773,9 +2493,6 @@ mod tests {
 
     #[test]
     fn test_compilation_of_all_default_patterns() {
-        if ENGINE == Engine::Regex {
-            return;
-        }
         let grok = Grok::default();
         let mut num_checked = 0;
         let mut errors = vec![];
@@ -797,57 +2514,1244 @@ mod tests {
     }
 
     #[test]
-    fn test_adhoc_pattern() {
-        let grok = Grok::default();
+    fn test_ecs_v1_pattern_set() {
+        let grok = Grok::with_pattern_set(PatternSet::EcsV1);
+        for &(key, _) in PatternSet::EcsV1.patterns() {
+            let pattern = format!("%{{{}}}", key);
+            if let Err(e) = grok.compile(&pattern, false) {
+                panic!("Pattern {} failed to compile: {}", key, e);
+            }
+        }
+        for &(key, _) in PatternSet::EcsV1.linear_patterns() {
+            assert!(PatternSet::EcsV1.patterns().iter().any(|&(k, _)| k == key));
+        }
+
         let pattern = grok
-            .compile(r"\[(?<threadname>[^\]]+)\]", false)
+            .compile("%{HTTPD_COMMONLOG}", true)
             .expect("Error while compiling!");
-
         let matches = pattern
-            .match_against("[thread1]")
+            .match_against(r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#)
             .expect("No matches found!");
-        assert_eq!("thread1", matches.get("threadname").unwrap());
-        assert_eq!(matches.len(), 1);
+        assert_eq!(Some("127.0.0.1"), matches.get("[source][address]"));
+        assert_eq!(Some("GET"), matches.get("[http][request][method]"));
+        assert_eq!(Some("200"), matches.get("[http][response][status_code]"));
+        assert_eq!(None, matches.get("clientip"));
+
+        assert_eq!(PatternSet::Legacy.patterns(), patterns());
+        assert_eq!(
+            Grok::default().patterns,
+            Grok::with_pattern_set(PatternSet::default()).patterns
+        );
     }
 
     #[test]
-    fn test_adhoc_pattern_in_iter() {
-        let grok = Grok::default();
-        let pattern = grok
-            .compile(r"\[(?<threadname>[^\]]+)\]", false)
-            .expect("Error while compiling!");
+    fn test_pattern_groups() {
+        let names = PatternGroup::ALL
+            .iter()
+            .map(|group| group.name())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(PatternGroup::ALL.len(), names.len());
+        assert!(names.contains("linux-syslog"));
+        assert_eq!("linux-syslog", PatternGroup::LinuxSyslog.to_string());
+
+        // Every group can be selected with a cargo feature.
+        let manifest = include_str!("../Cargo.toml");
+        for name in names {
+            assert!(
+                manifest.contains(&format!("\npatterns-{name} = []\n")),
+                "Cargo.toml is missing the patterns-{name} feature"
+            );
+        }
+
+        assert_eq!(&[PatternGroup::Grok], PatternGroup::Httpd.dependencies());
+        assert!(PatternGroup::Httpd.is_enabled());
+        assert!(PatternGroup::Grok.is_enabled());
+        assert_eq!(
+            cfg!(any(
+                not(feature = "select-patterns"),
+                feature = "patterns-aws"
+            )),
+            PatternGroup::Aws.is_enabled()
+        );
 
+        let grok = Grok::with_pattern_groups(PatternSet::Legacy, [PatternGroup::Httpd]);
+        assert!(grok.patterns.contains_key("HTTPD_COMMONLOG"));
+        assert!(grok.patterns.contains_key("USERNAME"));
+        assert!(!grok.patterns.contains_key("CISCO_ACTION"));
+        let pattern = grok.compile("%{HTTPD_COMMONLOG}", true).unwrap();
         let matches = pattern
-            .match_against("[thread1]")
-            .expect("No matches found!");
-        let mut found = 0;
-        assert_eq!(matches.len(), 1);
-        for (k, v) in matches.iter() {
-            assert_eq!("threadname", k);
-            assert_eq!("thread1", v);
-            found += 1;
+            .match_against(r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#)
+            .unwrap();
+        assert_eq!(Some("127.0.0.1"), matches.get("clientip"));
+
+        let grok = Grok::with_pattern_groups(PatternSet::EcsV1, [PatternGroup::Httpd]);
+        let pattern = grok.compile("%{HTTPD_COMMONLOG}", true).unwrap();
+        let matches = pattern
+            .match_against(r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#)
+            .unwrap();
+        assert_eq!(Some("127.0.0.1"), matches.get("[source][address]"));
+
+        // Selecting every group is the same as loading the whole set.
+        for set in [PatternSet::Legacy, PatternSet::EcsV1] {
+            assert_eq!(
+                Grok::with_pattern_set(set).patterns,
+                Grok::with_pattern_groups(set, PatternGroup::ALL.iter().copied()).patterns
+            );
+            assert_eq!(
+                set.patterns().len(),
+                PatternGroup::ALL
+                    .iter()
+                    .map(|group| group.patterns(set).count())
+                    .sum::<usize>()
+            );
         }
-        assert_eq!(1, found);
     }
 
-    /// If multiple captures have the same name, the last one wins.
     #[test]
-    fn test_adhoc_pattern_conflict() {
-        let grok = Grok::with_default_patterns();
-        let pattern = grok
-            .compile(r"(?<capture>\w+) %{GREEDYDATA:capture}", true)
+    fn test_dependencies() {
+        let mut grok = Grok::default();
+        grok.add_namespace_pattern("acme", "ID", "%{INT}");
+        let expanded = grok
+            .expand(r"%{acme::ID} %{HOSTPORT} %{X=\w+} %{WORD}", true)
             .unwrap();
-        assert_eq!(vec!["capture"], Vec::from_iter(pattern.capture_names()));
-        let matches = pattern.match_against("word1 word2").unwrap();
-        assert_eq!("word2", matches.get("capture").unwrap());
+        let dependencies = expanded.dependencies().iter().map(String::as_str);
+        assert_eq!(
+            vec![
+                "HOSTNAME", "HOSTPORT", "INT", "IP", "IPORHOST", "IPV4", "IPV6", "POSINT", "WORD",
+                "acme::ID"
+            ],
+            dependencies.collect::<Vec<_>>()
+        );
+        let expanded = grok.expand("%{httpd::HTTPD_COMMONLOG}", true).unwrap();
+        assert!(expanded.dependencies().contains("httpd::HTTPD_COMMONLOG"));
+        assert!(expanded.dependencies().contains("grok::IPORHOST"));
     }
 
     #[test]
-    fn test_capture_repeat() {
-        let grok = Grok::with_default_patterns();
-        let pattern = grok.compile(r"%{INT}{1,3}", false).unwrap();
-        let matches = pattern.match_against("+1+2+3").unwrap();
-        assert_eq!("+3", matches.get("INT").unwrap());
+    fn test_cache() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Grok>();
+
+        let mut grok = Grok::default();
+        assert_eq!(0, grok.cache_capacity());
+        let first = grok.compile_cached("%{WORD}", true).unwrap();
+        assert!(!Arc::ptr_eq(
+            &first,
+            &grok.compile_cached("%{WORD}", true).unwrap()
+        ));
+        assert_eq!(0, grok.cache_len());
+
+        grok.set_cache_capacity(2);
+        let word = grok.compile_cached("%{WORD}", true).unwrap();
+        let log = grok.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap();
+        assert!(Arc::ptr_eq(
+            &word,
+            &grok.compile_cached("%{WORD}", true).unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &word,
+            &grok.compile_cached("%{WORD}", false).unwrap()
+        ));
+        assert_eq!(2, grok.cache_len());
+
+        // The least recently used pattern was dropped.
+        assert!(!Arc::ptr_eq(
+            &log,
+            &grok.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &word,
+            &grok.compile_cached("%{WORD}", true).unwrap()
+        ));
+
+        // Only the patterns depending on a replaced pattern are dropped.
+        grok.set_cache_capacity(8);
+        let word = grok.compile_cached("%{WORD}", true).unwrap();
+        let log = grok.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap();
+        grok.add_pattern("IPORHOST", "[a-z]+");
+        assert!(Arc::ptr_eq(
+            &word,
+            &grok.compile_cached("%{WORD}", true).unwrap()
+        ));
+        let replaced = grok.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap();
+        assert!(!Arc::ptr_eq(&log, &replaced));
+        assert!(replaced
+            .match_against(r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#)
+            .is_none());
+        grok.add_namespace_pattern("acme", "WORD", "x");
+        assert!(!Arc::ptr_eq(
+            &word,
+            &grok.compile_cached("%{WORD}", true).unwrap()
+        ));
+
+        // Clones share the cached patterns.
+        let replaced = grok.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap();
+        let clone = grok.clone();
+        assert!(Arc::ptr_eq(
+            &replaced,
+            &clone.compile_cached("%{HTTPD_COMMONLOG}", true).unwrap()
+        ));
+        grok.set_cache_capacity(1);
+        assert_eq!(1, grok.cache_len());
+        grok.clear_cache();
+        assert_eq!(0, grok.cache_len());
+        assert!(grok.compile_cached("", true).is_err());
+    }
+
+    #[test]
+    fn test_pattern_registry() {
+        let mut grok = Grok::default();
+        assert!(grok.contains_pattern("USERNAME"));
+        assert_eq!(Some(r"[a-zA-Z0-9._-]+"), grok.get_pattern("USERNAME"));
+        assert_eq!(
+            Some(PatternOrigin::Builtin),
+            grok.pattern_origin("USERNAME")
+        );
+        assert_eq!(patterns().len(), grok.iter_patterns().count(),);
+
+        grok.add_pattern("USERNAME", "[a-z]+");
+        grok.add_pattern("TICKET", "T-%{INT}");
+        assert_eq!(Some(PatternOrigin::User), grok.pattern_origin("USERNAME"));
+        assert_eq!(Some(PatternOrigin::User), grok.pattern_origin("TICKET"));
+        assert_eq!(None, grok.pattern_origin("MISSING"));
+        assert_eq!(
+            Some(("TICKET", "T-%{INT}")),
+            grok.iter_patterns().find(|(name, _)| *name == "TICKET")
+        );
+        let names = grok
+            .iter_patterns()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(Some("T-%{INT}".to_string()), grok.remove_pattern("TICKET"));
+        assert_eq!(None, grok.remove_pattern("TICKET"));
+        assert!(!grok.contains_pattern("TICKET"));
+        assert!(grok.compile("%{TICKET}", true).is_err());
+
+        // Removing a pattern of a child reveals the pattern of the parent.
+        let mut child = Grok::with_parent(Arc::new(grok));
+        child.add_pattern("USERNAME", "[0-9]+");
+        assert_eq!(Some("[0-9]+"), child.get_pattern("USERNAME"));
+        assert!(child.remove_pattern("USERNAME").is_some());
+        assert_eq!(Some("[a-z]+"), child.get_pattern("USERNAME"));
+        assert_eq!(None, child.remove_pattern("USERNAME"));
+        assert_eq!(Some(PatternOrigin::Builtin), child.pattern_origin("WORD"));
+    }
+
+    #[test]
+    fn test_try_add_pattern() {
+        let mut grok = Grok::default();
+        assert_eq!(Ok(()), grok.try_add_pattern("TICKET", "T-%{INT:id}", true));
+        assert_eq!(Ok(()), grok.try_add_pattern("LATER", "%{NOT_YET}", false));
+        assert!(grok.contains_pattern("TICKET"));
+        assert!(grok.contains_pattern("LATER"));
+
+        let error = grok
+            .try_add_pattern("BAD", "%{INT} %{INT!} %{INT}", true)
+            .unwrap_err();
+        assert_eq!(
+            PatternDefinitionError::Parse {
+                range: 7..14,
+                error: GrokPatternError::InvalidCharacter {
+                    character: '!',
+                    range: 12..13,
+                    expected: ExpectedToken::OneOf(&[':', '=', '}']),
+                },
+            },
+            error
+        );
+        assert_eq!(
+            "The pattern definition has an invalid character '!' \
+             (expected one of ':', '=' or '}') at 7..14",
+            error.to_string()
+        );
+        assert_eq!(
+            Err(PatternDefinitionError::Parse {
+                range: 2..9,
+                error: GrokPatternError::InvalidPatternDefinition {
+                    range: 6..9,
+                    expected: ExpectedToken::OneOf(&['}']),
+                },
+            }),
+            grok.try_add_pattern("BAD", "a %{A=b c", false)
+        );
+        assert_eq!(
+            Err(PatternDefinitionError::Parse {
+                range: 8..15,
+                error: GrokPatternError::InvalidCharacter {
+                    character: '!',
+                    range: 12..13,
+                    expected: ExpectedToken::OneOf(&[':', '=', '}']),
+                },
+            }),
+            grok.try_add_pattern("BAD", "a %{A=b %{IN!T}{2}}", false)
+        );
+        assert_eq!(
+            Err(PatternDefinitionError::Compile {
+                range: 3..13,
+                error: Error::DefinitionNotFound("MISSING".to_string()),
+            }),
+            grok.try_add_pattern("BAD", "a (%{MISSING})", true)
+        );
+        assert_eq!(
+            Err(PatternDefinitionError::Compile {
+                range: 12..22,
+                error: Error::DefinitionNotFound("MISSING".to_string()),
+            }),
+            grok.try_add_pattern("BAD", "a %{A=b %{B=%{MISSING}}}", true)
+        );
+        let error = grok.try_add_pattern("BAD", "a [", true).unwrap_err();
+        assert_eq!(0..3, error.range());
+        assert!(matches!(
+            error,
+            PatternDefinitionError::Compile {
+                error: Error::RegexCompilationFailed(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            Err(PatternDefinitionError::Compile {
+                range: 0..6,
+                error: Error::CyclicDefinition(vec!["BAD".to_string(), "BAD".to_string()]),
+            }),
+            grok.try_add_pattern("BAD", "%{BAD}", true)
+        );
+        assert!(!grok.contains_pattern("BAD"));
+    }
+
+    #[test]
+    fn test_cyclic_definitions() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "a%{B}");
+        grok.add_pattern("B", "b%{C}?");
+        grok.add_pattern("C", "%{D:d}|%{A}");
+        grok.add_pattern("D", "d");
+        grok.add_pattern("E", "%{D} %{D} %{X=d+} %{X}");
+
+        let error = grok.compile("x%{A}", true).unwrap_err();
+        assert_eq!(
+            Error::CyclicDefinition(["A", "B", "C", "A"].into_iter().map(String::from).collect()),
+            error
+        );
+        assert_eq!(
+            "The given pattern definitions form a cycle: A -> B -> C -> A",
+            error.to_string()
+        );
+        assert_eq!(
+            Err(Error::CyclicDefinition(
+                ["B", "C", "A", "B"].into_iter().map(String::from).collect()
+            )),
+            grok.compile("%{D} %{B}", true).map(|_| ())
+        );
+        assert!(grok.compile("%{E}", true).is_ok());
+
+        // Namespaces and overrides are part of the cycle.
+        grok.add_namespace_pattern("acme", "A", "%{B}");
+        grok.add_namespace_pattern("acme", "B", "%{acme::A}");
+        assert_eq!(
+            Err(Error::CyclicDefinition(
+                ["acme::A", "acme::B", "acme::A"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )),
+            grok.compile("%{acme::A}", true).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::CyclicDefinition(
+                ["D", "D"].into_iter().map(String::from).collect()
+            )),
+            grok.compile_with("%{E}", &[("D", "%{D}")], true)
+                .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_parse_resolves_placeholders() {
+        use crate::parser::{Node, Placeholder, Visitor};
+
+        // Collects the names of the resolved placeholders, following them
+        struct Resolved(Vec<String>);
+        impl<'a> Visitor<'a> for Resolved {
+            fn visit(&mut self, node: &Node<'a>) {
+                if let Node::Placeholder(Placeholder {
+                    name,
+                    resolved: Some(resolved),
+                    ..
+                }) = node
+                {
+                    self.0.push(name.to_string());
+                    resolved.visit(self);
+                }
+                parser::visit_children(self, node);
+            }
+        }
+
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "%{B}-(%{C=%{B}})?%{C}");
+        grok.add_pattern("B", r"\d+");
+        grok.add_namespace_pattern("ns", "B", "%{A}");
+        let ast = grok.parse("%{A:a} %{ns::B}").unwrap();
+        let mut resolved = Resolved(vec![]);
+        ast.visit(&mut resolved);
+        assert_eq!(
+            vec!["A", "B", "B", "C", "B", "ns::B", "A", "B", "B", "C", "B"],
+            resolved.0
+        );
+        assert_eq!("%{A:a} %{ns::B}", ast.to_string());
+
+        assert_eq!(
+            Err(Error::DefinitionNotFound("MISSING".to_string())),
+            grok.parse("%{A}%{MISSING}").map(|_| ())
+        );
+        grok.add_pattern("B", "%{A}");
+        assert_eq!(
+            Err(Error::CyclicDefinition(
+                ["A", "B", "A"].into_iter().map(String::from).collect()
+            )),
+            grok.parse("%{A}").map(|_| ())
+        );
+        assert!(matches!(
+            grok.parse("(%{A}"),
+            Err(Error::GenericCompilationFailure(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_engine() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Debug)]
+        struct TestEngine(Arc<AtomicUsize>);
+
+        #[derive(Debug)]
+        struct TestRegex(::regex::Regex);
+
+        impl GrokEngine for TestEngine {
+            fn dialect(&self) -> dialect::Dialect {
+                dialect::Dialect::Regex
+            }
+
+            fn compile(&self, regex: &str) -> Result<Box<dyn CompiledRegex>, Error> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                ::regex::Regex::new(regex)
+                    .map(|regex| Box::new(TestRegex(regex)) as Box<dyn CompiledRegex>)
+                    .map_err(|e| Error::RegexCompilationFailed(e.to_string()))
+            }
+        }
+
+        impl CompiledRegex for TestRegex {
+            fn capture_names(&self) -> Vec<(String, usize)> {
+                self.0
+                    .capture_names()
+                    .enumerate()
+                    .filter_map(|(index, name)| Some((name?.to_string(), index)))
+                    .collect()
+            }
+
+            fn captures(&self, text: &str) -> Option<Vec<Option<Range<usize>>>> {
+                let captures = self.0.captures(text)?;
+                Some(captures.iter().map(|m| Some(m?.range())).collect())
+            }
+        }
+
+        let compiled = Arc::new(AtomicUsize::new(0));
+        let mut grok = Grok::empty();
+        grok.add_pattern("NUM", r"\d+");
+        grok.set_engine(TestEngine(compiled.clone()));
+        let pattern = grok
+            .compile(r"%{NUM:a}(?:-%{NUM:b})?[[:xdigit:]](?<raw>x)", false)
+            .unwrap();
+        assert_eq!(1, compiled.load(Ordering::Relaxed));
+        // Written for the engine, so passed to it unchanged
+        assert!(pattern.regex_source().contains("[[:xdigit:]]"));
+        assert_eq!(
+            vec!["a", "b", "raw"],
+            pattern.capture_names().collect::<Vec<_>>()
+        );
+
+        let matches = pattern.match_against("12fx").unwrap();
+        assert_eq!(Some("12"), matches.get("a"));
+        assert_eq!(None, matches.get("b"));
+        assert_eq!(
+            vec![("a", "12"), ("raw", "x")],
+            matches.iter().collect::<Vec<_>>()
+        );
+        assert!(pattern.match_against("12").is_none());
+        assert!(pattern.is_match("-12fx"));
+        assert_eq!(Some(1..5), pattern.find("-12fx"));
+
+        // The engine is inherited by layered instances
+        let child = Grok::with_parent(Arc::new(grok));
+        assert!(child.compile("%{NUM}", false).is_ok());
+        assert_eq!(2, compiled.load(Ordering::Relaxed));
+        assert!(matches!(
+            child.compile("(?<=a)b", false),
+            Err(Error::RegexCompilationFailed(_))
+        ));
+        assert!(matches!(
+            child.compile(r"\p{Nope}", false),
+            Err(Error::RegexCompilationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(r"%{INT:x} (?<x>\d+)(?: %{INT:x})?", true)
+            .unwrap();
+        assert_eq!(
+            vec!["x", "x[1]"],
+            pattern.capture_names().collect::<Vec<_>>()
+        );
+        let matches = pattern.match_against("1 2 3").unwrap();
+        assert_eq!(Some("2"), matches.get("x"));
+        assert_eq!(Some("3"), matches.get("x[1]"));
+        assert_eq!(vec!["1", "2", "3"], matches.get_all("x"));
+        assert_eq!(vec![("x", "2"), ("x[1]", "3")], matches.collect::<Vec<_>>());
+
+        // The last group that took part in the match wins
+        let pattern = grok.compile(r"(?:%{INT:x}|(?<x>[a-z]+))", true).unwrap();
+        let matches = pattern.match_against("5").unwrap();
+        assert_eq!(Some("5"), matches.get("x"));
+        assert_eq!(vec!["5"], matches.get_all("x"));
+        assert_eq!(vec![("x", "5")], matches.collect::<Vec<_>>());
+        let matches = pattern.match_against("a").unwrap();
+        assert_eq!(Some("a"), matches.get("x"));
+        assert!(matches.get_all("y").is_empty());
+
+        // Only Oniguruma allows repeated group names in a regular expression
+        if ENGINE == Engine::Onig {
+            let pattern = grok.compile("(?<y>a)|(?<y>b)", true).unwrap();
+            let matches = pattern.match_against("b").unwrap();
+            assert_eq!(Some("b"), matches.get("y"));
+            assert_eq!(vec!["b"], matches.get_all("y"));
+        }
+    }
+
+    #[test]
+    fn test_capture_history() {
+        let mut grok = Grok::default();
+        let pattern = grok
+            .compile(r"(?:%{WORD:key}=%{INT:value} ?)+", true)
+            .unwrap();
+        let matches = pattern.match_against("a=1 b=2 c=3").unwrap();
+        assert_eq!(vec!["c"], matches.history("key"));
+
+        grok.set_capture_history(true);
+        assert!(grok.capture_history());
+        let pattern = grok
+            .compile(r"(?:%{WORD:key}=%{INT:value} ?)+", true)
+            .unwrap();
+        let matches = pattern.match_against("a=1 b=2 c=3").unwrap();
+        assert_eq!(Some("c"), matches.get("key"));
+        assert_eq!(vec!["a", "b", "c"], matches.history("key"));
+        assert_eq!(vec!["1", "2", "3"], matches.history("value"));
+        assert!(matches.history("missing").is_empty());
+
+        // Nested repetitions, and captures outside of them
+        let pattern = grok
+            .compile(
+                r"%{WORD:name}: (?:%{WORD:key}(?:,%{INT:n})* ?)+ %{WORD:name}",
+                true,
+            )
+            .unwrap();
+        let matches = pattern.match_against("x: a,1,2 b c,3 y").unwrap();
+        assert_eq!(vec!["x", "y"], matches.history("name"));
+        assert_eq!(vec!["a", "b", "c"], matches.history("key"));
+        assert_eq!(vec!["1", "2", "3"], matches.history("n"));
+
+        // Iterations are split like in the original match
+        let pattern = grok.compile("(?:(?<p>a)|(?<q>ab))+c", true).unwrap();
+        let matches = pattern.match_against("ababc").unwrap();
+        assert!(matches.history("p").is_empty());
+        assert_eq!(vec!["ab", "ab"], matches.history("q"));
+
+        // Groups repeated at most once have no history
+        let pattern = grok.compile("(?:%{WORD:w} ){0,1}%{WORD:w}", true).unwrap();
+        let matches = pattern.match_against("a b").unwrap();
+        assert_eq!(vec!["a", "b"], matches.history("w"));
+
+        // Oniguruma records the history while matching, the emulation matches
+        // each iteration on its own, without the text around it
+        let pattern = grok.compile(r"(?:a|\B(?<x>b))+", true).unwrap();
+        let matches = pattern.match_against("abb").unwrap();
+        if ENGINE == Engine::Onig {
+            assert_eq!(vec!["b", "b"], matches.history("x"));
+        } else {
+            assert!(matches.history("x").is_empty());
+        }
+
+        // Oniguruma only records the first 31 groups, the others are emulated
+        let groups: String = (0..31).map(|i| format!("(?<g{i}>x)")).collect();
+        let pattern = grok
+            .compile(&format!("{groups}(?:%{{INT:n}},?)+"), true)
+            .unwrap();
+        let text = format!("{}1,2,3", "x".repeat(31));
+        let matches = pattern.match_against(&text).unwrap();
+        assert_eq!(vec!["1", "2", "3"], matches.history("n"));
+
+        // Layered instances inherit the setting
+        let child = Grok::with_parent(Arc::new(grok));
+        let pattern = child.compile(r"(?:%{INT:n},?){2,}", true).unwrap();
+        let matches = pattern.match_against("1,2,3").unwrap();
+        assert_eq!(vec!["1", "2", "3"], matches.history("n"));
+    }
+
+    #[test]
+    fn test_match_tree() {
+        let mut grok = Grok::default();
+        grok.add_pattern("HOST_OR_IP", "%{IP:ip}|%{HOSTNAME:host}");
+        let pattern = grok
+            .compile("%{WORD:user}@%{HOST_OR_IP}(?::%{INT:port})?", false)
+            .unwrap();
+
+        let matches = pattern.match_against("root@::1").unwrap();
+        let tree = matches.tree();
+        assert_eq!(
+            vec!["WORD", "HOST_OR_IP"],
+            tree.iter().map(|node| node.pattern()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("user"), tree[0].alias());
+        assert_eq!("root", tree[0].value());
+        assert_eq!(None, tree[1].alias());
+        assert_eq!("HOST_OR_IP", tree[1].name());
+        assert_eq!(5..8, tree[1].span());
+
+        let ip = &tree[1].children()[0];
+        assert_eq!(1, tree[1].children().len());
+        assert_eq!(("IP", "ip", "::1"), (ip.pattern(), ip.name(), ip.value()));
+        assert_eq!(1, ip.children().len());
+        assert_eq!("IPV6", ip.children()[0].pattern());
+
+        let matches = pattern.match_against("root@example.org:22").unwrap();
+        let tree = matches.tree();
+        assert_eq!(3, tree.len());
+        assert_eq!("HOSTNAME", tree[1].children()[0].pattern());
+        assert_eq!(("INT", 17..19), (tree[2].pattern(), tree[2].span()));
+
+        // Placeholders without alias are left out, and their children attach
+        // to the closest enclosing placeholder that captures
+        let pattern = grok.compile("%{WORD:user}@%{HOST_OR_IP}", true).unwrap();
+        let matches = pattern.match_against("root@10.0.0.1").unwrap();
+        let tree = matches.tree();
+        assert_eq!(2, tree.len());
+        assert_eq!(("IP", Some("ip")), (tree[1].pattern(), tree[1].alias()));
+        assert!(tree[1].children().is_empty());
+
+        // Repeated aliases keep their capture name
+        let pattern = grok.compile("%{INT:n},%{INT:n}", true).unwrap();
+        let matches = pattern.match_against("1,2").unwrap();
+        let names = matches.tree();
+        let names = names.iter().map(|node| node.name()).collect::<Vec<_>>();
+        assert_eq!(vec!["n", "n[1]"], names);
+    }
+
+    #[test]
+    fn test_schema() {
+        let mut grok = Grok::default();
+        grok.add_pattern("PAIR", "%{WORD:key}=%{INT:value:int}");
+        grok.add_pattern("PAIRS", "%{PAIR}(?:,%{PAIR:extra})*");
+        let pattern = grok
+            .compile(
+                "%{PAIRS} (?<flag>[a-z])? (?:%{IP:addr}|%{HOSTNAME:addr})",
+                true,
+            )
+            .unwrap();
+        let schema = pattern.schema();
+        let summary = schema
+            .iter()
+            .map(|field| {
+                (
+                    field.name(),
+                    field.pattern(),
+                    field.is_optional(),
+                    field.extract(),
+                    field.path().join("/"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("key", Some("WORD"), false, None, "PAIRS/PAIR".to_string()),
+                (
+                    "value",
+                    Some("INT"),
+                    false,
+                    Some("int"),
+                    "PAIRS/PAIR".to_string()
+                ),
+                ("extra", Some("PAIR"), true, None, "PAIRS".to_string()),
+                ("key[1]", Some("WORD"), true, None, "PAIRS/PAIR".to_string()),
+                (
+                    "value[1]",
+                    Some("INT"),
+                    true,
+                    Some("int"),
+                    "PAIRS/PAIR".to_string()
+                ),
+                ("flag", None, true, None, String::new()),
+                ("addr", Some("IP"), true, None, String::new()),
+                ("addr[1]", Some("HOSTNAME"), true, None, String::new()),
+            ],
+            summary
+        );
+
+        // A name captured by several groups is only optional if every group is
+        let pattern = grok.compile("(?<n>a)?%{INT:n}", true).unwrap();
+        let schema = pattern.schema();
+        assert_eq!(1, schema.len());
+        assert!(!schema[0].is_optional());
+
+        // Named groups of a pattern are found in that pattern
+        grok.add_pattern("FLAGGED", "(?<flag>!)%{WORD:word}");
+        let pattern = grok.compile("%{FLAGGED:f}", true).unwrap();
+        let schema = pattern.schema();
+        let names = schema.iter().map(|field| field.name()).collect::<Vec<_>>();
+        assert_eq!(vec!["f", "flag", "word"], names);
+        assert_eq!(["FLAGGED".to_string()], schema[1].path());
+        assert!(schema.iter().all(|field| !field.is_optional()));
+    }
+
+    #[test]
+    fn test_is_match_and_find() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(r"%{WORD:verb} %{URIPATH:path}(?: %{INT:status})?", false)
+            .unwrap();
+        for text in [
+            "GET /index.html 200",
+            "> POST /api -",
+            "DELETE",
+            "",
+            "é GET /é 404 trailing",
+        ] {
+            let matches = pattern.match_against(text);
+            assert_eq!(matches.is_some(), pattern.is_match(text), "{text}");
+            // The whole match is the span from the first to the last capture
+            let span = matches.map(|matches| {
+                let first = matches.get("verb").unwrap();
+                let last = matches.get("status").or(matches.get("path")).unwrap();
+                let start = first.as_ptr() as usize - text.as_ptr() as usize;
+                start..last.as_ptr() as usize + last.len() - text.as_ptr() as usize
+            });
+            assert_eq!(span, pattern.find(text), "{text}");
+        }
+
+        // The match found is the same as with the capture groups
+        let pattern = grok.compile(r"a|ab|(?<x>abc)", false).unwrap();
+        assert_eq!(Some(1..2), pattern.find("xabc"));
+        let pattern = grok.compile(r"\bb+", false).unwrap();
+        assert_eq!(Some(4..6), pattern.find("abb bb"));
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("A", "%{B}");
+        grok.add_pattern("B", "%{C}");
+        grok.add_pattern("C", "c");
+        assert_eq!(MAX_RECURSION, grok.recursion_limit());
+
+        grok.set_recursion_limit(3);
+        assert!(grok.compile("%{B}", true).is_ok());
+        assert_eq!(
+            Err(Error::RecursionTooDeep(3)),
+            grok.compile("%{A}", true).map(|_| ())
+        );
+        assert!(grok.parse("%{B}").is_ok());
+        assert_eq!(
+            Err(Error::RecursionTooDeep(3)),
+            grok.parse("%{A}").map(|_| ())
+        );
+        assert_eq!(
+            "Recursion while compiling reached the limit of 3",
+            grok.compile("%{A}", true).unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(Error::RecursionTooDeep(3)),
+            Grok::with_parent(Arc::new(grok))
+                .compile("%{A}", true)
+                .map(|_| ())
+        );
+
+        let mut grok = Grok::default();
+        grok.set_recursion_limit(10);
+        assert!(grok.compile("%{HTTPD_COMBINEDLOG}", true).is_ok());
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-redis"))]
+    #[test]
+    fn test_extend() {
+        let mut other = Grok::with_pattern_groups(PatternSet::Legacy, [PatternGroup::Redis]);
+        other.add_pattern("TICKET", "T-%{INT}");
+        other.add_namespace_pattern("acme", "LINE", "%{WORD} %{TICKET}");
+        other.import_namespace("acme", "grok");
+
+        let mut grok = Grok::empty();
+        grok.add_pattern("TICKET", "X-%{INT}");
+        grok.extend(&other);
+        assert_eq!(
+            &[ShadowWarning::Pattern {
+                namespace: None,
+                name: "TICKET".to_string()
+            }],
+            grok.warnings()
+        );
+        assert_eq!(Some("T-%{INT}"), grok.get_pattern("TICKET"));
+        assert_eq!(
+            Some(PatternOrigin::Builtin),
+            grok.pattern_origin("REDISLOG")
+        );
+        assert_eq!(Some(PatternOrigin::User), grok.pattern_origin("TICKET"));
+        assert_eq!(other.iter_patterns().count(), grok.iter_patterns().count());
+        assert!(grok
+            .compile("%{acme::LINE}", true)
+            .unwrap()
+            .match_against("hello T-1")
+            .is_some());
+    }
+
+    #[test]
+    fn test_layered() {
+        let mut base = Grok::with_pattern_set(PatternSet::EcsV1);
+        base.add_pattern("TICKET", "T-%{INT}");
+        base.add_namespace_pattern("acme", "ID", "[a-z]+");
+        base.import_namespace("acme", "grok");
+        let base = Arc::new(base);
+
+        let mut tenant = Grok::with_parent(base.clone());
+        assert!(Arc::ptr_eq(&base, tenant.parent().unwrap()));
+        tenant.add_pattern("TICKET", "TT-%{INT}");
+        tenant.add_pattern("PRIORITY", "P[0-9]");
+        tenant.add_namespace_pattern("acme", "LINE", "%{ID:id} %{WORD:word}");
+        assert_eq!(
+            &[ShadowWarning::Pattern {
+                namespace: None,
+                name: "TICKET".to_string()
+            }],
+            tenant.warnings()
+        );
+
+        // The child sees its own patterns first, then the parent's.
+        let pattern = tenant.compile("%{TICKET} %{PRIORITY}", true).unwrap();
+        assert!(pattern.match_against("TT-1 P1").is_some());
+        assert!(pattern.match_against("T-1 P1").is_none());
+        let pattern = tenant.compile("%{acme::LINE}", true).unwrap();
+        let matches = pattern.match_against("abc def").unwrap();
+        assert_eq!(Some("abc"), matches.get("id"));
+        let pattern = tenant.compile("%{httpd::HTTPD_COMMONLOG}", false).unwrap();
+        assert!(pattern
+            .capture_names()
+            .any(|name| name == "[source][address]"));
+
+        // The parent is unchanged.
+        assert!(base
+            .compile("%{TICKET}", true)
+            .unwrap()
+            .match_against("T-1")
+            .is_some());
+        assert!(base.compile("%{PRIORITY}", true).is_err());
+        assert!(base.compile("%{acme::LINE}", true).is_err());
+
+        // Layers stack, and the imports of a namespace accumulate.
+        let tenant = Arc::new(tenant);
+        let mut team = Grok::with_parent(tenant.clone());
+        team.import_namespace("acme", "grok");
+        team.import_namespace("acme", "linux_syslog");
+        team.add_namespace_pattern("acme", "ID", "[0-9]+");
+        assert_eq!(
+            vec![ShadowWarning::Pattern {
+                namespace: Some("acme".to_string()),
+                name: "ID".to_string()
+            }],
+            team.warnings()
+        );
+        let pattern = team.compile("%{acme::LINE} %{PRIORITY}", true).unwrap();
+        assert!(pattern.match_against("123 def P2").is_some());
+        assert!(team.compile("%{acme::SYSLOGPROG}", true).is_err());
+        assert_eq!(vec!["grok", "linux_syslog"], team.namespace_imports("acme"));
+        assert_eq!(vec!["grok"], tenant.namespace_imports("acme"));
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-haproxy"))]
+    #[test]
+    fn test_compile_with() {
+        const LINE: &str = r#"Sep 14 15:35:17 localhost haproxy[14389]: 127.0.0.1:33317 [14/Sep/2024:15:35:17.123] http-in static/srv1 10/0/30/69/109 200 2750 - - ---- 1/1/1/1/0 0/0 {example.com|curl} "GET /index.html HTTP/1.1""#;
+
+        let grok = Grok::default();
+        let overrides = [(
+            "HAPROXYCAPTUREDREQUESTHEADERS",
+            r"%{DATA:request_header_host}\|%{DATA:request_header_user_agent}",
+        )];
+        let pattern = grok
+            .compile_with("%{HAPROXYHTTP}", &overrides, true)
+            .unwrap();
+        let matches = pattern.match_against(LINE).unwrap();
+        assert_eq!(Some("example.com"), matches.get("request_header_host"));
+        assert_eq!(Some("curl"), matches.get("request_header_user_agent"));
+        assert_eq!(None, matches.get("captured_request_headers"));
+
+        // The registry itself is unchanged.
+        let pattern = grok.compile("%{HAPROXYHTTP}", true).unwrap();
+        let matches = pattern.match_against(LINE).unwrap();
+        assert_eq!(
+            Some("example.com|curl"),
+            matches.get("captured_request_headers")
+        );
+
+        // Overrides reach into built-in namespaces, and can add new names.
+        let pattern = grok
+            .compile_with("%{haproxy::HAPROXYHTTP}", &overrides, true)
+            .unwrap();
+        assert!(pattern
+            .capture_names()
+            .any(|name| name == "request_header_host"));
+        let pattern = grok
+            .compile_with("%{GREETING}", &[("GREETING", "%{WORD:word}!")], true)
+            .unwrap();
+        assert_eq!(
+            Some("hello"),
+            pattern.match_against("hello!").unwrap().get("word")
+        );
+
+        // Inline definitions take precedence, and namespaced references need a
+        // namespaced override.
+        let overrides = [("INT", "x"), ("grok::WORD", "y")];
+        let expanded = grok
+            .expand_with(
+                "%{INT=z} %{INT} %{grok::WORD} %{grok::INT}",
+                &overrides,
+                true,
+            )
+            .unwrap();
+        assert!(expanded
+            .regex()
+            .starts_with("(?:z) (?:z) (?:y) (?:(?:[+-]?(?:[0-9]+)))"));
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-linux-syslog"))]
+    #[test]
+    fn test_namespaces() {
+        const LINE: &str = r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#;
+
+        // Built-in namespaces are available even without the global patterns.
+        let grok = Grok::empty();
+        let pattern = grok.compile("%{httpd::HTTPD_COMMONLOG}", true).unwrap();
+        let matches = pattern.match_against(LINE).unwrap();
+        assert_eq!(Some("127.0.0.1"), matches.get("clientip"));
+        assert_eq!(
+            Err(Error::DefinitionNotFound("httpd::NOPE".to_string())),
+            grok.compile("%{httpd::NOPE}", true).map(|_| ())
+        );
+        assert!(grok
+            .compile("%{linux_syslog::SYSLOGLINE:line}", true)
+            .is_ok());
+
+        // Namespaced patterns are not affected by the global ones.
+        let mut grok = Grok::default();
+        grok.add_pattern("IPORHOST", "nope");
+        assert!(grok
+            .compile("%{HTTPD_COMMONLOG}", true)
+            .unwrap()
+            .match_against(LINE)
+            .is_none());
+        assert!(grok
+            .compile("%{httpd::HTTPD_COMMONLOG}", true)
+            .unwrap()
+            .match_against(LINE)
+            .is_some());
+
+        // `%{NAME::extract}` still has an empty alias when NAME is not a namespace.
+        let expanded = grok.expand("%{INT::int}", false).unwrap();
+        assert_eq!(Some(&"int".to_string()), expanded.extracts().get("INT"));
+        assert_eq!(
+            Err(Error::DefinitionNotFound("NOPE".to_string())),
+            grok.compile("%{NOPE::int}", false).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::DefinitionNotFound("NOPE::a".to_string())),
+            grok.compile("%{NOPE::a:b}", false).map(|_| ())
+        );
+
+        // The ECS set has its own namespaces.
+        let grok = Grok::with_pattern_set(PatternSet::EcsV1);
+        let pattern = grok.compile("%{httpd::HTTPD_COMMONLOG}", true).unwrap();
+        let matches = pattern.match_against(LINE).unwrap();
+        assert_eq!(Some("127.0.0.1"), matches.get("[source][address]"));
+    }
+
+    #[test]
+    fn test_namespace_imports() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("ID", "global");
+        grok.add_namespace_pattern("acme", "ID", r"[0-9]+");
+        grok.add_namespace_pattern("acme", "LINE", r"%{WORD:user}=%{ID:id}");
+        assert_eq!(
+            Err(Error::DefinitionNotFound("WORD".to_string())),
+            grok.compile("%{acme::LINE}", true).map(|_| ())
+        );
+
+        grok.import_namespace("acme", "grok");
+        let pattern = grok.compile("%{acme::LINE}", true).unwrap();
+        let matches = pattern.match_against("alice=42").unwrap();
+        assert_eq!(Some("alice"), matches.get("user"));
+        assert_eq!(Some("42"), matches.get("id"));
+        assert!(grok
+            .compile("%{ID}", true)
+            .unwrap()
+            .match_against("42")
+            .is_none());
+
+        // Namespaces import each other's patterns, but not transitively.
+        grok.add_namespace_pattern("other", "LINE2", "%{LINE} %{USERNAME}");
+        grok.import_namespace("other", "acme");
+        assert_eq!(
+            Err(Error::DefinitionNotFound("USERNAME".to_string())),
+            grok.compile("%{other::LINE2}", true).map(|_| ())
+        );
+        grok.add_pattern("USERNAME", r"\w+");
+        assert!(grok
+            .compile("%{other::LINE2}", true)
+            .unwrap()
+            .match_against("alice=42 bob")
+            .is_some());
+        assert!(grok.warnings().is_empty());
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-linux-syslog"))]
+    #[test]
+    fn test_shadow_warnings() {
+        let mut grok = Grok::default();
+        grok.add_pattern("WORD", r"\b\w+\b");
+        assert!(grok.warnings().is_empty());
+        grok.add_pattern("WORD", r"\w+");
+        grok.add_pattern("WORD", r"\w+");
+        assert_eq!(
+            &[ShadowWarning::Pattern {
+                namespace: None,
+                name: "WORD".to_string()
+            }],
+            grok.warnings()
+        );
+        assert_eq!(
+            "Pattern WORD replaces an existing definition",
+            grok.warnings()[0].to_string()
+        );
+        grok.clear_warnings();
+
+        grok.import_namespace("acme", "grok");
+        grok.add_namespace_pattern("acme", "INT", r"\d+");
+        grok.add_namespace_pattern("acme", "INT", r"[0-9]+");
+        grok.add_namespace_pattern("acme", "LINE", r"%{INT}");
+        grok.import_namespace("acme", "linux_syslog");
+        grok.add_namespace_pattern("httpd", "HTTPD_COMMONLOG", r"%{INT}");
+        assert_eq!(
+            vec![
+                ShadowWarning::Import {
+                    namespace: "acme".to_string(),
+                    import: "grok".to_string(),
+                    name: "INT".to_string(),
+                },
+                ShadowWarning::Pattern {
+                    namespace: Some("acme".to_string()),
+                    name: "INT".to_string(),
+                },
+                ShadowWarning::Import {
+                    namespace: "acme".to_string(),
+                    import: "grok".to_string(),
+                    name: "INT".to_string(),
+                },
+                ShadowWarning::Namespace {
+                    namespace: "httpd".to_string(),
+                },
+            ],
+            grok.warnings()
+        );
+
+        // A namespace added by the user replaces the built-in one.
+        assert_eq!(
+            Err(Error::DefinitionNotFound(
+                "httpd::HTTPD_COMBINEDLOG".to_string()
+            )),
+            grok.compile("%{httpd::HTTPD_COMBINEDLOG}", true)
+                .map(|_| ())
+        );
+        assert!(grok
+            .compile("%{acme::LINE}", true)
+            .unwrap()
+            .match_against("1")
+            .is_some());
+
+        // Imports hidden by earlier imports are reported.
+        grok.clear_warnings();
+        grok.import_namespace("both", "grok");
+        grok.import_namespace("both", "linux_syslog");
+        grok.import_namespace("both", "grok");
+        assert!(grok.warnings().is_empty());
+        grok.add_namespace_pattern("mine", "SYSLOGLINE", ".*");
+        grok.import_namespace("both", "mine");
+        assert_eq!(
+            vec![ShadowWarning::Import {
+                namespace: "both".to_string(),
+                import: "mine".to_string(),
+                name: "SYSLOGLINE".to_string(),
+            }],
+            grok.warnings()
+        );
+    }
+
+    /// The linear-time replacements must agree with the patterns they replace
+    /// on a shared corpus, except for the documented differences, and with
+    /// the expected outcome on every engine. The original definitions are
+    /// checked on every engine that can compile them.
+    #[cfg(any(
+        not(feature = "select-patterns"),
+        all(
+            feature = "patterns-firewalls",
+            feature = "patterns-haproxy",
+            feature = "patterns-linux-syslog",
+            feature = "patterns-mongodb"
+        )
+    ))]
+    #[test]
+    fn test_linear_patterns_equivalence() {
+        let grok = Grok::with_default_patterns();
+        let mut linear = Grok::with_default_patterns();
+        for &(name, definition) in linear_patterns() {
+            linear.add_pattern(name, definition);
+        }
+
+        let mut num_checked = 0;
+        for line in include_str!("../testdata/LINEAR_CORPUS").lines() {
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+            if let Some(line) = line.strip_prefix('~') {
+                let (pattern, line) = line.split_once(' ').unwrap();
+                let (input, found) = line.rsplit_once(" => ").unwrap();
+                let (original_found, linear_found) =
+                    found.split_once(" | ").unwrap_or((found, found));
+                let find = |grok: &Grok| {
+                    let pattern = grok.compile(pattern, false).unwrap();
+                    let found = pattern.find(input).map(|span| input[span].to_string());
+                    found.unwrap_or_else(|| "<none>".to_string())
+                };
+                assert_eq!(
+                    linear_found,
+                    find(&linear),
+                    "{pattern} (linear) in {input:?}"
+                );
+                if ENGINE != Engine::Regex {
+                    assert_eq!(original_found, find(&grok), "{pattern} in {input:?}");
+                }
+                num_checked += 1;
+                continue;
+            }
+            let (name, input) = line.split_once(' ').unwrap();
+            let (name, expected) = match name.strip_prefix('!') {
+                Some(name) => (name, false),
+                None => (name, true),
+            };
+            let pattern = format!("^%{{{name}}}$");
+
+            let linear = linear.compile(&pattern, true).unwrap();
+            let linear_matches = linear.match_against(input);
+            assert_eq!(
+                expected,
+                linear_matches.is_some(),
+                "{name} (linear) on {input:?}"
+            );
+
+            if ENGINE != Engine::Regex {
+                let original = grok.compile(&pattern, true).unwrap();
+                let original_matches = original.match_against(input);
+                assert_eq!(
+                    original_matches
+                        .as_ref()
+                        .map(|m| m.collect::<BTreeMap<_, _>>()),
+                    linear_matches
+                        .as_ref()
+                        .map(|m| m.collect::<BTreeMap<_, _>>()),
+                    "{name} on {input:?}"
+                );
+            }
+            num_checked += 1;
+        }
+
+        // Every replacement must be covered by the corpus
+        let corpus = include_str!("../testdata/LINEAR_CORPUS");
+        for &(name, _) in linear_patterns() {
+            assert!(
+                corpus
+                    .lines()
+                    .any(|l| l.starts_with(&format!("{name} ")) || l == format!("# covers {name}")),
+                "{name} is not covered by the corpus"
+            );
+            assert!(
+                corpus
+                    .lines()
+                    .any(|l| l.starts_with('~') && l.contains(&format!("%{{{name}}}"))),
+                "{name} is not searched for in the corpus"
+            );
+        }
+        assert!(num_checked > 0);
+    }
+
+    #[test]
+    fn test_adhoc_pattern() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(r"\[(?<threadname>[^\]]+)\]", false)
+            .expect("Error while compiling!");
+
+        let matches = pattern
+            .match_against("[thread1]")
+            .expect("No matches found!");
+        assert_eq!("thread1", matches.get("threadname").unwrap());
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn test_adhoc_pattern_in_iter() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(r"\[(?<threadname>[^\]]+)\]", false)
+            .expect("Error while compiling!");
+
+        let matches = pattern
+            .match_against("[thread1]")
+            .expect("No matches found!");
+        let mut found = 0;
+        assert_eq!(matches.len(), 1);
+        for (k, v) in matches.iter() {
+            assert_eq!("threadname", k);
+            assert_eq!("thread1", v);
+            found += 1;
+        }
+        assert_eq!(1, found);
+    }
+
+    /// If multiple captures have the same name, the last one wins.
+    #[test]
+    fn test_adhoc_pattern_conflict() {
+        let grok = Grok::with_default_patterns();
+        let pattern = grok
+            .compile(r"(?<capture>\w+) %{GREEDYDATA:capture}", true)
+            .unwrap();
+        assert_eq!(vec!["capture"], Vec::from_iter(pattern.capture_names()));
+        let matches = pattern.match_against("word1 word2").unwrap();
+        assert_eq!("word2", matches.get("capture").unwrap());
+    }
+
+    #[test]
+    fn test_capture_repeat() {
+        let grok = Grok::with_default_patterns();
+        let pattern = grok.compile(r"%{INT}{1,3}", false).unwrap();
+        let matches = pattern.match_against("+1+2+3").unwrap();
+        assert_eq!("+3", matches.get("INT").unwrap());
     }
 
     #[test]
@@ -861,6 +3765,133 @@ mod tests {
         assert_eq!("word2", matches.get("second").unwrap());
     }
 
+    #[test]
+    fn test_escaped_placeholder() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(r"^%{INT:percent}\%{%{WORD:word}}$", true)
+            .unwrap();
+        let matches = pattern.match_against("100%{done}").unwrap();
+        assert_eq!(Some("100"), matches.get("percent"));
+        assert_eq!(Some("done"), matches.get("word"));
+        assert_eq!(1, pattern.capture_names().count() - 1);
+
+        let pattern = grok
+            .compile(r"^%{BRACED:value=\%{%{WORD}\}}$", true)
+            .unwrap();
+        let matches = pattern.match_against("%{hello}").unwrap();
+        assert_eq!(Some("%{hello}"), matches.get("value"));
+        assert!(pattern.match_against("%hello}").is_none());
+
+        // Like in Logstash, `%%{` is a literal `%` followed by a placeholder
+        let pattern = grok.compile("^%%{WORD:tag}$", true).unwrap();
+        let matches = pattern.match_against("%ASA").unwrap();
+        assert_eq!(Some("ASA"), matches.get("tag"));
+    }
+
+    #[test]
+    fn test_pattern_with_braces_in_definition() {
+        let grok = Grok::default();
+        let pattern = grok
+            .compile(
+                r"^%{CODE:code=[A-Z]{3}}-%{ID:id=(?:%{INT}[.]){2}%{INT}}$",
+                true,
+            )
+            .unwrap();
+        let matches = pattern.match_against("ABC-1.2.3").unwrap();
+        assert_eq!(Some("ABC"), matches.get("code"));
+        assert_eq!(Some("1.2.3"), matches.get("id"));
+        assert!(pattern.match_against("ABCD-1.2.3").is_none());
+
+        let pattern = grok
+            .compile(r"^%{BRACED:value=\{%{WORD}\}}$", true)
+            .unwrap();
+        let matches = pattern.match_against("{hello}").unwrap();
+        assert_eq!(Some("{hello}"), matches.get("value"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut grok = Grok::empty();
+        grok.add_pattern("USERNAME", r"[a-zA-Z0-9._-]+");
+        grok.add_pattern("USER", r"%{USERNAME}");
+
+        let expanded = grok.expand("%{USER:usr:text} %{USERNAME}", false).unwrap();
+        assert_eq!(
+            r"(?<_n_0>(?<_n_1>[a-zA-Z0-9._-]+)) (?<_n_2>[a-zA-Z0-9._-]+)",
+            expanded.regex()
+        );
+        assert_eq!("usr", expanded.aliases()["_n_0"]);
+        assert_eq!("USERNAME", expanded.aliases()["_n_1"]);
+        assert_eq!("USERNAME[1]", expanded.aliases()["_n_2"]);
+        assert_eq!(
+            Some("text"),
+            expanded.extracts().get("usr").map(|s| s.as_str())
+        );
+
+        let expanded = grok.expand("%{USER:usr}", true).unwrap();
+        assert_eq!(r"(?<_n_0>(?:[a-zA-Z0-9._-]+))", expanded.regex());
+
+        let pattern = grok.compile("%{USER:usr}", true).unwrap();
+        assert_eq!(expanded.regex(), pattern.regex_source());
+    }
+
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-rails"))]
+    #[test]
+    fn test_dialect_translation() {
+        let grok = Grok::with_default_patterns();
+
+        // `\h` is a hex digit in grok patterns, regardless of the engine.
+        let pattern = grok.compile("^%{RUUID}$", false).unwrap();
+        assert!(pattern
+            .match_against("0123456789abcdef0123456789ABCDEF")
+            .is_some());
+        assert!(pattern.match_against(&" ".repeat(32)).is_none());
+
+        let pattern = grok.compile("^%{UNIXPATH}$", false).unwrap();
+        assert!(pattern.match_against("/usr/local/bin").is_some());
+        assert!(pattern.match_against("/usr/local/bin[]").is_none());
+        let expanded = grok.expand("^%{UNIXPATH}$", false).unwrap();
+        let approximations = pattern
+            .approximations()
+            .iter()
+            .map(|issue| &expanded.regex()[issue.range.clone()])
+            .collect::<Vec<_>>();
+        if ENGINE == Engine::Onig {
+            assert!(approximations.is_empty());
+        } else {
+            assert_eq!(vec!["[:alnum:]"], approximations);
+        }
+
+        // Look-behind written in a pattern is left to the engine to reject
+        if ENGINE == Engine::Regex {
+            assert!(matches!(
+                grok.compile(r"(?<!\\)%{QS}", false),
+                Err(Error::RegexCompilationFailed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_user_regex_is_not_translated() {
+        let mut grok = Grok::with_default_patterns();
+        grok.add_pattern("DOTTED", r"(?m)\w+\.\w+");
+        let pattern = grok.compile(r"(?m)%{WORD:w}$|%{DOTTED:d}", false).unwrap();
+
+        // Written for the active engine, so passed to it unchanged: `(?m)` is
+        // multi-line mode rather than dot-all mode except on Oniguruma, where
+        // `$` always matches at the end of a line
+        let source = pattern.regex_source();
+        assert!(source.starts_with("(?m)(?<_n_0>"));
+        assert!(source.ends_with(r"$|(?<_n_1>(?m)\w+\.\w+)"));
+        assert!(pattern.approximations().is_empty());
+
+        let matches = pattern.match_against("x\ny").unwrap();
+        assert_eq!(Some("x"), matches.get("w"));
+        let matches = pattern.match_against("a.b ").unwrap();
+        assert_eq!(Some("a.b"), matches.get("d"));
+    }
+
     #[test]
     fn test_capture_names() {
         let mut grok = Grok::empty();
@@ -905,10 +3936,6 @@ mod tests {
 
     #[test]
     fn test_capture_error() {
-        if ENGINE == Engine::Regex {
-            return;
-        }
-
         let grok = Grok::with_default_patterns();
         let pattern = grok
             .compile("Path: %{PATH}$", false)
@@ -919,12 +3946,9 @@ mod tests {
         assert!(matches.is_none());
     }
 
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-bacula"))]
     #[test]
     fn test_match_deep_patterns() {
-        if ENGINE == Engine::Regex {
-            return;
-        }
-
         let grok = Grok::with_default_patterns();
         let pattern = grok
             .compile("%{BACULA_LOGLINE}", false)
@@ -970,6 +3994,7 @@ mod tests {
         eprintln!("{:?}", matches);
     }
 
+    #[cfg(any(not(feature = "select-patterns"), feature = "patterns-bacula"))]
     #[test]
     fn test_compile_deep_patterns() {
         if ENGINE == Engine::Regex {
@@ -977,18 +4002,18 @@ mod tests {
         }
 
         let grok = Grok::with_default_patterns();
-        let pattern = grok
-            .compile("%{BACULA_LOGLINE}", false)
-            .expect("Error while compiling!");
+        let expanded = grok
+            .expand("%{BACULA_LOGLINE}", false)
+            .expect("Error while expanding!");
 
-        assert_eq!(pattern.text, include_str!("../testdata/BACULA_LOGLINE"));
+        assert_eq!(expanded.regex(), include_str!("../testdata/BACULA_LOGLINE"));
 
-        let pattern = grok
-            .compile("%{BACULA_LOGLINE}", true)
-            .expect("Error while compiling!");
+        let expanded = grok
+            .expand("%{BACULA_LOGLINE}", true)
+            .expect("Error while expanding!");
 
         assert_eq!(
-            pattern.text,
+            expanded.regex(),
             include_str!("../testdata/BACULA_LOGLINE.aliasesonly")
         );
 
@@ -996,6 +4021,14 @@ mod tests {
             .compile("%{ELB_ACCESS_LOG}", false)
             .expect("Error while compiling!");
 
-        assert_eq!(pattern.text, include_str!("../testdata/ELB_ACCESS_LOG"));
+        if ENGINE == Engine::Onig {
+            assert_eq!(
+                pattern.regex_source(),
+                include_str!("../testdata/ELB_ACCESS_LOG")
+            );
+        } else {
+            let expanded = grok.expand("%{ELB_ACCESS_LOG}", false).unwrap();
+            assert_eq!(expanded.regex(), include_str!("../testdata/ELB_ACCESS_LOG"));
+        }
     }
 }
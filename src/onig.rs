@@ -1,6 +1,11 @@
+use crate::ast::{self, rewrite_children, Group, GroupKind, Node, Rewriter};
 use crate::Error;
-use onig::{MatchParam, Regex, Region, SearchOptions};
-use std::collections::{btree_map, BTreeMap, HashMap};
+use onig::{
+    CaptureTreeNode, MatchParam, Regex, RegexOptions, Region, SearchOptions, Syntax, SyntaxOperator,
+};
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::OnceLock;
 
 pub(crate) const ENGINE: crate::Engine = crate::Engine::Onig;
 
@@ -8,7 +13,12 @@ pub(crate) const ENGINE: crate::Engine = crate::Engine::Onig;
 #[derive(Debug)]
 pub(crate) struct OnigPattern {
     pub regex: Regex,
-    pub names: BTreeMap<String, u32>,
+    pub names: BTreeMap<String, Vec<usize>>,
+    source: String,
+    /// The regex without capture groups, compiled on first use by
+    /// [`OnigPattern::is_match`] and [`OnigPattern::find`]. `None` if it could
+    /// not be compiled, such as when a group is referred to.
+    uncaptured: OnceLock<Option<Regex>>,
 }
 
 impl OnigPattern {
@@ -17,22 +27,25 @@ impl OnigPattern {
     pub(crate) fn new(regex: &str, alias: &HashMap<String, String>) -> Result<Self, Error> {
         match Regex::new(regex) {
             Ok(r) => Ok({
-                let mut names = BTreeMap::new();
+                let mut groups = vec![];
                 r.foreach_name(|cap_name, cap_idx| {
-                    let name = alias.get(cap_name).map_or(cap_name, |s| s).to_string();
-                    match names.entry(name) {
-                        btree_map::Entry::Vacant(e) => {
-                            e.insert(cap_idx[0]);
-                        }
-                        btree_map::Entry::Occupied(mut e) => {
-                            if cap_idx[0] > *e.get() {
-                                e.insert(cap_idx[0]);
-                            }
-                        }
-                    }
+                    groups.extend(
+                        cap_idx
+                            .iter()
+                            .map(|&idx| (idx as usize, cap_name.to_string())),
+                    );
                     true
                 });
-                Self { regex: r, names }
+                let names = crate::group_names(
+                    groups.iter().map(|(idx, name)| (*idx, name.as_str())),
+                    alias,
+                );
+                Self {
+                    regex: r,
+                    names,
+                    source: regex.to_string(),
+                    uncaptured: OnceLock::new(),
+                }
             }),
             Err(e) => Err(Error::RegexCompilationFailed(format!(
                 "Regex compilation failed: {e:?}:\n{regex}"
@@ -57,6 +70,45 @@ impl OnigPattern {
         })
     }
 
+    /// Returns whether the pattern matches somewhere in the text, searching
+    /// without capture groups.
+    pub fn is_match(&self, text: &str) -> bool {
+        let options = SearchOptions::SEARCH_OPTION_NONE;
+        let match_param = MatchParam::default();
+        let result =
+            self.uncaptured()
+                .search_with_param(text, 0, text.len(), options, None, match_param);
+        result.unwrap_or_default().is_some()
+    }
+
+    /// Returns the span of the first match in the text, searching without
+    /// capture groups.
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        let mut region = Region::new();
+        let options = SearchOptions::SEARCH_OPTION_NONE;
+        let match_param = MatchParam::default();
+        let result = self.uncaptured().search_with_param(
+            text,
+            0,
+            text.len(),
+            options,
+            Some(&mut region),
+            match_param,
+        );
+        result.unwrap_or_default()?;
+        region.pos(0).map(|(start, end)| start..end)
+    }
+
+    /// Returns the regex without capture groups, or the regex itself if it
+    /// could not be compiled.
+    fn uncaptured(&self) -> &Regex {
+        let uncaptured = self.uncaptured.get_or_init(|| {
+            let ast = ast::parse(&self.source).ok()?;
+            Regex::new(&ast.rewrite(&mut Uncapture).to_string()).ok()
+        });
+        uncaptured.as_ref().unwrap_or(&self.regex)
+    }
+
     /// Returns all names this `Pattern` captures.
     pub fn capture_names(&self) -> impl Iterator<Item = &str> {
         self.names.keys().map(|s| s.as_str())
@@ -74,13 +126,23 @@ pub(crate) struct OnigMatches<'a> {
 impl<'a> OnigMatches<'a> {
     /// Gets the value for the name (or) alias if found, `None` otherwise.
     pub fn get(&self, name_or_alias: &str) -> Option<&str> {
-        match self.pattern.names.get(name_or_alias) {
-            Some(found) => self
-                .region
-                .pos(*found as usize)
-                .map(|(start, end)| &self.text[start..end]),
-            None => None,
-        }
+        self.pattern
+            .names
+            .get(name_or_alias)?
+            .iter()
+            .rev()
+            .find_map(|&idx| self.region.pos(idx))
+            .map(|(start, end)| &self.text[start..end])
+    }
+
+    /// Gets the values of every group with the name (or) alias that took part
+    /// in the match, along with their index.
+    pub fn get_all(&self, name_or_alias: &str) -> Vec<(usize, &str)> {
+        let indices = self.pattern.names.get(name_or_alias).into_iter().flatten();
+        indices
+            .filter_map(|&idx| Some((idx, self.region.pos(idx)?)))
+            .map(|(idx, (start, end))| (idx, &self.text[start..end]))
+            .collect()
     }
 
     /// Returns a tuple of key/value with all the matches found.
@@ -108,7 +170,7 @@ impl<'a> IntoIterator for &'a OnigMatches<'a> {
 pub(crate) struct OnigMatchesIter<'a> {
     text: &'a str,
     region: &'a Region,
-    names: btree_map::Iter<'a, String, u32>,
+    names: btree_map::Iter<'a, String, Vec<usize>>,
 }
 
 impl<'a> Iterator for OnigMatchesIter<'a> {
@@ -116,7 +178,7 @@ impl<'a> Iterator for OnigMatchesIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         for (k, v) in self.names.by_ref() {
-            match self.region.pos(*v as usize) {
+            match v.iter().rev().find_map(|&idx| self.region.pos(idx)) {
                 Some((start, end)) => return Some((k.as_str(), &self.text[start..end])),
                 None => {
                     continue;
@@ -126,3 +188,168 @@ impl<'a> Iterator for OnigMatchesIter<'a> {
         None
     }
 }
+
+/// The highest group number Oniguruma records the capture history of.
+const MAX_HISTORY_GROUP: usize = 31;
+
+/// A pattern recording every iteration of the named groups inside repeated
+/// groups with Oniguruma's capture history, see [`crate::history`].
+#[derive(Debug)]
+pub(crate) struct OnigHistory {
+    regex: Regex,
+    names: BTreeMap<String, Vec<usize>>,
+    /// The groups whose capture history is recorded.
+    recorded: BTreeSet<usize>,
+}
+
+impl OnigHistory {
+    /// Compiles a regex recording the capture history of its repeated named
+    /// groups, or returns `None` if it has none, or if some of them are beyond
+    /// the groups Oniguruma can record.
+    pub(crate) fn new(regex: &str, alias: &HashMap<String, String>) -> Result<Option<Self>, Error> {
+        let ast = ast::parse(regex)
+            .map_err(|e| Error::GenericCompilationFailure(format!("{e}:\n{regex}")))?;
+        let repeated = crate::history::repeated_names(&ast);
+        if repeated.is_empty() {
+            return Ok(None);
+        }
+        let mut record = RecordHistory {
+            repeated: &repeated,
+            groups: 0,
+            recorded: BTreeSet::new(),
+        };
+        let regex = ast.rewrite(&mut record).to_string();
+        if record.recorded.last() > Some(&MAX_HISTORY_GROUP) {
+            return Ok(None);
+        }
+
+        let mut syntax = *Syntax::default();
+        syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_ATMARK_CAPTURE_HISTORY);
+        let regex =
+            Regex::with_options(&regex, RegexOptions::REGEX_OPTION_NONE, &syntax).map_err(|e| {
+                Error::RegexCompilationFailed(format!("Regex compilation failed: {e:?}:\n{regex}"))
+            })?;
+        let mut groups = vec![];
+        regex.foreach_name(|name, indices| {
+            groups.extend(indices.iter().map(|&idx| (idx as usize, name.to_string())));
+            true
+        });
+        let names = crate::group_names(
+            groups.iter().map(|(idx, name)| (*idx, name.as_str())),
+            alias,
+        );
+        Ok(Some(Self {
+            regex,
+            names,
+            recorded: record.recorded,
+        }))
+    }
+
+    /// Matches the text, returning the spans of every value captured by the
+    /// groups selected by `is_key`.
+    pub(crate) fn spans(&self, text: &str, is_key: &dyn Fn(&str) -> bool) -> Vec<Range<usize>> {
+        let mut region = Region::new();
+        let options = SearchOptions::SEARCH_OPTION_NONE;
+        let match_param = MatchParam::default();
+        let result = self.regex.search_with_param(
+            text,
+            0,
+            text.len(),
+            options,
+            Some(&mut region),
+            match_param,
+        );
+        if result.unwrap_or_default().is_none() {
+            return vec![];
+        }
+
+        let mut spans = vec![];
+        let mut recorded = BTreeSet::new();
+        for (_, indices) in self.names.iter().filter(|(key, _)| is_key(key)) {
+            for &idx in indices {
+                if self.recorded.contains(&idx) {
+                    recorded.insert(idx);
+                } else if let Some((start, end)) = region.pos(idx) {
+                    spans.push(start..end);
+                }
+            }
+        }
+        if let Some(tree) = region.tree() {
+            collect_history(tree, &recorded, &mut spans);
+        }
+        spans
+    }
+}
+
+/// Collects the spans of the captures of the given groups below a node of the
+/// capture history.
+fn collect_history(
+    node: &CaptureTreeNode,
+    groups: &BTreeSet<usize>,
+    spans: &mut Vec<Range<usize>>,
+) {
+    for child in node.children() {
+        if groups.contains(&child.group()) {
+            let (start, end) = child.pos();
+            spans.push(start..end);
+        }
+        collect_history(child, groups, spans);
+    }
+}
+
+/// Records the capture history of the named groups with the given names, and
+/// the numbers of these groups.
+struct RecordHistory<'r> {
+    repeated: &'r BTreeSet<String>,
+    /// The number of named groups seen so far.
+    groups: usize,
+    recorded: BTreeSet<usize>,
+}
+
+impl<'a> Rewriter<'a> for RecordHistory<'_> {
+    fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+        match node {
+            Node::Group(Group {
+                kind: GroupKind::Named(name),
+                open,
+                nodes,
+            }) => {
+                // Named groups are numbered in the order they open
+                self.groups += 1;
+                let open = if self.repeated.contains(name.as_ref()) {
+                    self.recorded.insert(self.groups);
+                    format!("(?@<{name}>").into()
+                } else {
+                    open
+                };
+                let group = Group {
+                    kind: GroupKind::Named(name),
+                    open,
+                    nodes,
+                };
+                rewrite_children(self, Node::Group(group))
+            }
+            node => rewrite_children(self, node),
+        }
+    }
+}
+
+/// Turns capturing groups into non-capturing groups.
+struct Uncapture;
+
+impl<'a> Rewriter<'a> for Uncapture {
+    fn rewrite(&mut self, node: Node<'a>) -> Node<'a> {
+        match rewrite_children(self, node) {
+            Node::Group(Group {
+                kind: GroupKind::Named(_) | GroupKind::Capturing,
+                nodes,
+                ..
+            }) => Node::Group(Group {
+                kind: GroupKind::NonCapturing,
+                open: "(?:".into(),
+                nodes,
+            }),
+            node => node,
+        }
+    }
+}
@@ -1,18 +1,133 @@
 use std::{iter::Peekable, ops::Range, str::CharIndices};
 
+/// The characters that may end the name or alias of a placeholder.
+const TERMINATORS: &[char] = &[':', '=', '}'];
+/// The characters that may end the extract of a placeholder.
+const EXTRACT_TERMINATORS: &[char] = &['=', '}'];
+
 /// An error in the grok pattern.
-#[derive(Debug)]
+///
+/// Each error carries the byte range of the offending part of the pattern and
+/// what was expected there instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GrokPatternError {
     /// The pattern could not be parsed successfully.
-    InvalidCharacter(char),
+    InvalidCharacter {
+        /// The invalid character.
+        character: char,
+        /// The byte range of the character.
+        range: Range<usize>,
+        /// What was expected instead.
+        expected: ExpectedToken,
+    },
     /// The pattern is invalid.
-    InvalidPattern,
+    InvalidPattern {
+        /// The byte range of the error, which is empty at the end of the
+        /// pattern.
+        range: Range<usize>,
+        /// What was expected instead.
+        expected: ExpectedToken,
+    },
     /// The pattern definition is invalid.
-    InvalidPatternDefinition,
+    InvalidPatternDefinition {
+        /// The byte range of the error.
+        range: Range<usize>,
+        /// What was expected instead.
+        expected: ExpectedToken,
+    },
 }
 
-/// One of the components of a grok pattern: a regular expression, a pattern or
-/// an error.
+impl GrokPatternError {
+    /// Returns the byte range of the error in the pattern.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            GrokPatternError::InvalidCharacter { range, .. }
+            | GrokPatternError::InvalidPattern { range, .. }
+            | GrokPatternError::InvalidPatternDefinition { range, .. } => range.clone(),
+        }
+    }
+
+    /// Returns what was expected where the error occurred.
+    pub fn expected(&self) -> ExpectedToken {
+        match self {
+            GrokPatternError::InvalidCharacter { expected, .. }
+            | GrokPatternError::InvalidPattern { expected, .. }
+            | GrokPatternError::InvalidPatternDefinition { expected, .. } => *expected,
+        }
+    }
+
+    /// Moves the range of the error by `offset` bytes, for errors in a part of
+    /// a larger pattern.
+    pub(crate) fn offset(mut self, offset: usize) -> Self {
+        match &mut self {
+            GrokPatternError::InvalidCharacter { range, .. }
+            | GrokPatternError::InvalidPattern { range, .. }
+            | GrokPatternError::InvalidPatternDefinition { range, .. } => {
+                *range = range.start + offset..range.end + offset;
+            }
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for GrokPatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrokPatternError::InvalidCharacter { character, .. } => {
+                write!(f, "invalid character {character:?}")
+            }
+            GrokPatternError::InvalidPattern { .. } => f.write_str("invalid pattern"),
+            GrokPatternError::InvalidPatternDefinition { .. } => {
+                f.write_str("invalid pattern definition")
+            }
+        }?;
+        write!(f, " (expected {})", self.expected())
+    }
+}
+
+/// What the parser expected where a [`GrokPatternError`] occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExpectedToken {
+    /// The name of a pattern.
+    Name,
+    /// The alias of a pattern.
+    Alias,
+    /// The extract of a pattern.
+    Extract,
+    /// A non-empty inline definition.
+    Definition,
+    /// One of the given characters.
+    OneOf(&'static [char]),
+}
+
+impl std::fmt::Display for ExpectedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedToken::Name => f.write_str("a pattern name"),
+            ExpectedToken::Alias => f.write_str("an alias"),
+            ExpectedToken::Extract => f.write_str("an extract"),
+            ExpectedToken::Definition => f.write_str("a definition"),
+            ExpectedToken::OneOf([c]) => write!(f, "{c:?}"),
+            ExpectedToken::OneOf(chars) => {
+                f.write_str("one of ")?;
+                for (i, c) in chars.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        i if i == chars.len() - 1 => f.write_str(" or ")?,
+                        _ => f.write_str(", ")?,
+                    }
+                    write!(f, "{c:?}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// One of the components of a grok pattern: a regular expression, a pattern,
+/// an escape or an error.
+#[non_exhaustive]
 pub enum GrokComponent<'a> {
     /// This chunk is a regular expression.
     RegularExpression {
@@ -36,6 +151,13 @@ pub enum GrokComponent<'a> {
         /// The definition part of the pattern.
         definition: &'a str,
     },
+    /// This chunk is a `\%{` escape, which stands for a literal `%{`.
+    Escape {
+        /// The span of the original string.
+        range: Range<usize>,
+        /// The text chunk of the original string.
+        string: &'a str,
+    },
     /// The pattern could not be parsed successfully.
     PatternError(GrokPatternError),
 }
@@ -44,6 +166,7 @@ impl std::fmt::Debug for GrokComponent<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             GrokComponent::RegularExpression{ string, .. } => write!(f, "{string:?}"),
+            GrokComponent::Escape{ string, .. } => write!(f, "<escape {string:?}>"),
             GrokComponent::GrokPattern{ name, alias, extract: capture, definition, .. } => write!(f, "%{{ name={name:?} alias={alias:?} capture={capture:?} definition={definition:?} }}"),
             GrokComponent::PatternError(e) => write!(f, "<error {e:?}>"),
         }
@@ -53,7 +176,8 @@ impl std::fmt::Debug for GrokComponent<'_> {
 impl std::fmt::Display for GrokComponent<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            GrokComponent::RegularExpression { string, .. } => f.write_str(string),
+            GrokComponent::RegularExpression { string, .. }
+            | GrokComponent::Escape { string, .. } => f.write_str(string),
             GrokComponent::GrokPattern { pattern, .. } => f.write_str(pattern),
             GrokComponent::PatternError(e) => write!(f, "<error {e:?}>"),
         }
@@ -64,6 +188,7 @@ impl std::fmt::Display for GrokComponent<'_> {
 pub struct GrokSplit<'a> {
     string: &'a str,
     string_iter: Peekable<CharIndices<'a>>,
+    recover: bool,
 }
 
 impl<'a> Iterator for GrokSplit<'a> {
@@ -72,10 +197,14 @@ impl<'a> Iterator for GrokSplit<'a> {
     /// Hand-rolled state machine
     fn next(&mut self) -> Option<Self::Item> {
         match self.try_next() {
-            res @ Some(GrokComponent::PatternError(_)) => {
-                // Fuse the iterator if we error out
-                self.string_iter = "".char_indices().peekable();
-                res
+            Some(GrokComponent::PatternError(e)) => {
+                if self.recover {
+                    self.skip_placeholder(e.range().start);
+                } else {
+                    // Fuse the iterator if we error out
+                    self.string_iter = "".char_indices().peekable();
+                }
+                Some(GrokComponent::PatternError(e))
             }
             res => res,
         }
@@ -83,41 +212,81 @@ impl<'a> Iterator for GrokSplit<'a> {
 }
 
 impl<'a> GrokSplit<'a> {
+    /// Continues past errors instead of ending after the first one, so that
+    /// every error of a pattern can be reported at once.
+    ///
+    /// After an error, the rest of the invalid placeholder is skipped, up to
+    /// and including its closing `}`, or up to the next `%{`.
+    ///
+    /// ```
+    /// use grok::parser::{grok_split, GrokComponent};
+    ///
+    /// let errors = grok_split("%{A!} %{B} %{C:}")
+    ///     .with_recovery()
+    ///     .filter_map(|c| match c {
+    ///         GrokComponent::PatternError(e) => Some(e.range()),
+    ///         _ => None,
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(vec![3..4, 15..16], errors);
+    /// ```
+    pub fn with_recovery(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Resumes parsing after the placeholder containing the error at `start`.
+    fn skip_placeholder(&mut self, start: usize) {
+        // An unterminated placeholder has already consumed the rest of the
+        // pattern
+        if self.string_iter.peek().is_none() {
+            return;
+        }
+        let rest = &self.string[start..];
+        let resume = match (rest.find('}'), rest.find("%{")) {
+            (Some(brace), Some(next)) if next < brace => start + next,
+            (Some(brace), _) => start + brace + 1,
+            (None, Some(next)) => start + next,
+            (None, None) => self.string.len(),
+        };
+        self.string_iter = self.string.char_indices().peekable();
+        while self.string_iter.next_if(|(i, _)| *i < resume).is_some() {}
+    }
+
     fn try_next(&mut self) -> Option<GrokComponent<'a>> {
         let (start, next) = self.string_iter.next()?;
 
-        if next == '%' {
-            // End of string, that's fine (skip updating index, we're done)
-            let Some((_, next)) = self.string_iter.next() else {
-                let range = start..self.string.len();
-                return Some(GrokComponent::RegularExpression {
-                    string: &self.string[range.clone()],
-                    range,
-                });
-            };
+        if next == '\\' && self.string[start..].starts_with(r"\%{") {
+            _ = self.string_iter.nth(1);
+            let range = start..start + 3;
+            return Some(GrokComponent::Escape {
+                string: &self.string[range.clone()],
+                range,
+            });
+        }
 
-            if next == '{' {
+        if next == '%' {
+            // Any other character, including another `%`, starts a new chunk
+            if self.string_iter.next_if(|(_, c)| *c == '{').is_some() {
                 let mut components: [&'a str; 3] = ["", "", ""];
                 let mut comp_index = 0;
 
                 // Load up to three components for PATTERN:field:type, erroring out if
                 // more are present.
                 loop {
-                    match self.try_munch_word(comp_index > 0) {
+                    match self.try_munch_word(comp_index) {
                         Ok((terminator, word)) => {
-                            if comp_index == 3 {
-                                return Some(GrokComponent::PatternError(
-                                    GrokPatternError::InvalidPattern,
-                                ));
-                            }
-
                             components[comp_index] = word;
                             let next = self.string_iter.next().unwrap();
+                            let terminator_range = next.0..next.0 + 1;
                             comp_index += 1;
 
                             if comp_index == 3 && components[2].is_empty() {
                                 return Some(GrokComponent::PatternError(
-                                    GrokPatternError::InvalidPatternDefinition,
+                                    GrokPatternError::InvalidPatternDefinition {
+                                        range: terminator_range,
+                                        expected: ExpectedToken::Extract,
+                                    },
                                 ));
                             }
 
@@ -126,7 +295,10 @@ impl<'a> GrokSplit<'a> {
 
                                 if comp_index == 2 && components[1].is_empty() {
                                     return Some(GrokComponent::PatternError(
-                                        GrokPatternError::InvalidPatternDefinition,
+                                        GrokPatternError::InvalidPatternDefinition {
+                                            range: terminator_range,
+                                            expected: ExpectedToken::Alias,
+                                        },
                                     ));
                                 }
 
@@ -140,31 +312,46 @@ impl<'a> GrokSplit<'a> {
                                 });
                             } else if terminator == '=' {
                                 let definition_start = next.0 + 1;
+                                // The definition ends at the first unbalanced, unescaped `}`
+                                let mut depth = 0;
                                 let index = loop {
                                     let Some((index, next)) = self.string_iter.next() else {
                                         return Some(GrokComponent::PatternError(
-                                            GrokPatternError::InvalidPatternDefinition,
+                                            GrokPatternError::InvalidPatternDefinition {
+                                                range: definition_start..self.string.len(),
+                                                expected: ExpectedToken::OneOf(&['}']),
+                                            },
                                         ));
                                     };
-                                    if next == '{' {
-                                        return Some(GrokComponent::PatternError(
-                                            GrokPatternError::InvalidPatternDefinition,
-                                        ));
-                                    }
-                                    if next == '}' {
-                                        break index;
+                                    match next {
+                                        // A `\%{` escape has no closing brace
+                                        '\\' => {
+                                            if let Some((_, '%')) = self.string_iter.next() {
+                                                _ = self.string_iter.next_if(|(_, c)| *c == '{');
+                                            }
+                                        }
+                                        '{' => depth += 1,
+                                        '}' if depth == 0 => break index,
+                                        '}' => depth -= 1,
+                                        _ => {}
                                     }
                                 };
 
                                 let definition = &self.string[definition_start..index];
                                 if definition.is_empty() {
                                     return Some(GrokComponent::PatternError(
-                                        GrokPatternError::InvalidPatternDefinition,
+                                        GrokPatternError::InvalidPatternDefinition {
+                                            range: index..index + 1,
+                                            expected: ExpectedToken::Definition,
+                                        },
                                     ));
                                 }
                                 if comp_index == 2 && components[1].is_empty() {
                                     return Some(GrokComponent::PatternError(
-                                        GrokPatternError::InvalidPatternDefinition,
+                                        GrokPatternError::InvalidPatternDefinition {
+                                            range: terminator_range,
+                                            expected: ExpectedToken::Alias,
+                                        },
                                     ));
                                 }
 
@@ -176,6 +363,14 @@ impl<'a> GrokSplit<'a> {
                                     extract: components[2],
                                     definition,
                                 });
+                            } else if comp_index == 3 {
+                                // A fourth component
+                                return Some(GrokComponent::PatternError(
+                                    GrokPatternError::InvalidPattern {
+                                        range: terminator_range,
+                                        expected: ExpectedToken::OneOf(EXTRACT_TERMINATORS),
+                                    },
+                                ));
                             }
                         }
                         Err(e) => return Some(GrokComponent::PatternError(e)),
@@ -184,16 +379,22 @@ impl<'a> GrokSplit<'a> {
             }
         }
 
-        // Not a pattern, munch until end-of-string or a %
-        while let Some((index, next)) = self.string_iter.peek() {
-            if *next == '%' {
-                let range = start..*index;
+        // Not a pattern, munch until end-of-string, a % or a `\%{` escape
+        if next == '\\' {
+            _ = self.string_iter.next();
+        }
+        while let Some(&(index, next)) = self.string_iter.peek() {
+            if next == '%' || self.string[index..].starts_with(r"\%{") {
+                let range = start..index;
                 return Some(GrokComponent::RegularExpression {
                     string: &self.string[range.clone()],
                     range,
                 });
             }
             _ = self.string_iter.next();
+            if next == '\\' {
+                _ = self.string_iter.next();
+            }
         }
 
         let range = start..self.string.len();
@@ -204,41 +405,75 @@ impl<'a> GrokSplit<'a> {
     }
 
     /// Attempt to munch a word at the current index, Returns the terminator
-    /// character and the word.
-    fn try_munch_word(
-        &mut self,
-        is_alias_or_capture: bool,
-    ) -> Result<(char, &'a str), GrokPatternError> {
+    /// character and the word. `component` is the index of the word: the
+    /// name, alias or extract.
+    fn try_munch_word(&mut self, component: usize) -> Result<(char, &'a str), GrokPatternError> {
         let terminator;
+        let is_alias_or_capture = component > 0;
+        let (word, terminators) = match component {
+            0 => (ExpectedToken::Name, TERMINATORS),
+            1 => (ExpectedToken::Alias, TERMINATORS),
+            _ => (ExpectedToken::Extract, EXTRACT_TERMINATORS),
+        };
+        let end_of_pattern = |expected| GrokPatternError::InvalidPattern {
+            range: self.string.len()..self.string.len(),
+            expected,
+        };
 
         let Some((start, _)) = self.string_iter.peek() else {
-            return Err(GrokPatternError::InvalidPattern);
+            return Err(end_of_pattern(word));
         };
         let start = *start;
         let mut end;
+        let mut is_namespaced = false;
 
         loop {
-            if let Some((index, next)) = self.string_iter.peek() {
-                end = *index;
-                if *next == '}' || *next == '=' || *next == ':' {
-                    terminator = *next;
+            if let Some(&(index, next)) = self.string_iter.peek() {
+                end = index;
+                // A name may be qualified by a namespace once: `namespace::NAME`
+                if next == ':' && !is_alias_or_capture && !is_namespaced && end > start {
+                    let mut lookahead = self.string_iter.clone();
+                    lookahead.next();
+                    if lookahead.next().is_some_and(|(_, c)| c == ':')
+                        && lookahead
+                            .peek()
+                            .is_some_and(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                    {
+                        is_namespaced = true;
+                        self.string_iter = lookahead;
+                        continue;
+                    }
+                }
+                if next == '}' || next == '=' || next == ':' {
+                    terminator = next;
                     break;
                 }
                 // is_alias or is_capture allows for extra chars: `-[].`
                 if !next.is_ascii_alphanumeric()
-                    && *next != '_'
-                    && (!is_alias_or_capture || !"-[].".contains(*next))
+                    && next != '_'
+                    && (!is_alias_or_capture || !"-[].".contains(next))
                 {
-                    return Err(GrokPatternError::InvalidCharacter(*next));
+                    return Err(GrokPatternError::InvalidCharacter {
+                        character: next,
+                        range: index..index + next.len_utf8(),
+                        expected: if end == start {
+                            word
+                        } else {
+                            ExpectedToken::OneOf(terminators)
+                        },
+                    });
                 }
                 _ = self.string_iter.next();
             } else {
-                return Err(GrokPatternError::InvalidPattern);
+                return Err(end_of_pattern(ExpectedToken::OneOf(terminators)));
             }
         }
 
         if end == start && !is_alias_or_capture {
-            Err(GrokPatternError::InvalidPattern)
+            Err(GrokPatternError::InvalidPattern {
+                range: end..end + 1,
+                expected: ExpectedToken::Name,
+            })
         } else {
             Ok((terminator, &self.string[start..end]))
         }
@@ -256,24 +491,28 @@ impl<'a> GrokSplit<'a> {
 /// pattern, and `definition` is the definition of the pattern.
 ///
 /// - `name` is the name of the pattern and is required. It may contain any
-///   alphanumeric character, or `_`.
+///   alphanumeric character, or `_`, and may be qualified by a namespace as
+///   `namespace::name`. As `%{name::extract}` is also a pattern with an empty
+///   alias, such a name is reported as a whole and resolved when compiled.
 /// - `alias` is the alias of the pattern and is optional. It may contain any
 ///   alphanumeric character, or any of `_-[].`. If extract is provided,
 ///   `alias` may be empty.
 /// - `extract` is the extract of the pattern and is optional. It may contain
 ///   any alphanumeric character, or any of `_-[].`.
 /// - `definition` is the definition of the pattern and is optional. It may
-///   contain any character other than `{` or `}`.
+///   contain any character, as long as `{` and `}` are balanced or escaped as
+///   `\{` and `\}`, eg: `%{CODE=[A-Z]{3}}`.
 ///
 /// A literal `%` character may appear in a grok pattern as long as it is not
-/// followed by `{`. You can surround the percent with grouped parentheses
-/// `(%){..}`, a non-capturing group `(?:%){..}`, or use the `\x25` escape
-/// sequence, ie: `\x25{..}`.
+/// followed by `{`, so `%%{NAME}` is a literal `%` followed by a pattern. A
+/// literal `%{` is written as `\%{`, which is split into an
+/// [`GrokComponent::Escape`].
 pub fn grok_split<'a, S: AsRef<str> + ?Sized>(string: &'a S) -> GrokSplit<'a> {
     let string = string.as_ref();
     GrokSplit {
         string,
         string_iter: string.char_indices().peekable(),
+        recover: false,
     }
 }
 
@@ -304,6 +543,15 @@ mod tests {
             "%{name:name:name=defn}",
             "%{name:name[x]}",
             "%{name:name[x]:name[y]}",
+            "%{ns::name:name}",
+            "%{ns::name:name:name}",
+            "%{ns::name:name=defn}",
+            "%{name=a{3}}",
+            "%{name=[A-Z]{2,3}(?:a{1}){2}}",
+            r"%{name=\{}",
+            r"%{name=\}}",
+            r"%{name=a\\}",
+            "%{name=%{other:alias}{2}}",
         ] {
             eprintln!("{pattern} -> {:?}", grok_split(pattern).collect::<Vec<_>>());
             assert!(!grok_split(pattern).any(|c| matches!(c, GrokComponent::PatternError(_))));
@@ -331,6 +579,253 @@ mod tests {
         }
     }
 
+    #[test]
+    fn namespaced_grok_patterns() {
+        for (pattern, expected) in [
+            ("%{ns::NAME}", ("ns::NAME", "", "")),
+            ("%{ns::NAME:alias}", ("ns::NAME", "alias", "")),
+            ("%{ns::NAME:alias:int}", ("ns::NAME", "alias", "int")),
+            ("%{ns::NAME::int}", ("ns::NAME", "", "int")),
+            ("%{NAME::int}", ("NAME::int", "", "")),
+        ] {
+            let Some(GrokComponent::GrokPattern {
+                name,
+                alias,
+                extract,
+                ..
+            }) = grok_split(pattern).next()
+            else {
+                panic!("{pattern} should have parsed");
+            };
+            assert_eq!(expected, (name, alias, extract), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn inline_definitions() {
+        for (pattern, expected) in [
+            ("%{CODE=[A-Z]{3}}", "[A-Z]{3}"),
+            ("%{CODE=(?:[A-Z]{1,2}){3}}", "(?:[A-Z]{1,2}){3}"),
+            ("%{CODE:code=%{WORD}{2}}", "%{WORD}{2}"),
+            (r"%{BRACE=\{+}", r"\{+"),
+            (r"%{BRACE=\}+}", r"\}+"),
+            (r"%{BRACE=\{[^\}]*\}}", r"\{[^\}]*\}"),
+        ] {
+            let components = grok_split(pattern).collect::<Vec<_>>();
+            let [GrokComponent::GrokPattern {
+                definition, range, ..
+            }] = components.as_slice()
+            else {
+                panic!("{pattern} should have parsed: {components:?}");
+            };
+            assert_eq!(expected, *definition, "{pattern}");
+            assert_eq!(0..pattern.len(), *range, "{pattern}");
+        }
+
+        // The components after an inline definition are unaffected.
+        let components = grok_split("%{A=a{3}} %{B:b=\\}}-{1}")
+            .map(|c| format!("{c}"))
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["%{A=a{3}}", " ", "%{B:b=\\}}", "-{1}"], components);
+
+        // An unbalanced definition is an error, and ends the components.
+        let components = grok_split("a %{A=a{3} b %{B}").collect::<Vec<_>>();
+        assert!(
+            matches!(
+                components.as_slice(),
+                [
+                    GrokComponent::RegularExpression { .. },
+                    GrokComponent::PatternError(GrokPatternError::InvalidPatternDefinition { .. })
+                ]
+            ),
+            "{components:?}"
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        const NAME_END: ExpectedToken = ExpectedToken::OneOf(TERMINATORS);
+        const EXTRACT_END: ExpectedToken = ExpectedToken::OneOf(EXTRACT_TERMINATORS);
+        for (pattern, expected) in [
+            (
+                "a %{",
+                GrokPatternError::InvalidPattern {
+                    range: 4..4,
+                    expected: ExpectedToken::Name,
+                },
+            ),
+            (
+                "%{name",
+                GrokPatternError::InvalidPattern {
+                    range: 6..6,
+                    expected: NAME_END,
+                },
+            ),
+            (
+                "%{}",
+                GrokPatternError::InvalidPattern {
+                    range: 2..3,
+                    expected: ExpectedToken::Name,
+                },
+            ),
+            (
+                "%{!}",
+                GrokPatternError::InvalidCharacter {
+                    character: '!',
+                    range: 2..3,
+                    expected: ExpectedToken::Name,
+                },
+            ),
+            (
+                "%{na.me:a}",
+                GrokPatternError::InvalidCharacter {
+                    character: '.',
+                    range: 4..5,
+                    expected: NAME_END,
+                },
+            ),
+            (
+                "%{name:a:b€}",
+                GrokPatternError::InvalidCharacter {
+                    character: '€',
+                    range: 10..13,
+                    expected: EXTRACT_END,
+                },
+            ),
+            (
+                "%{name:}",
+                GrokPatternError::InvalidPatternDefinition {
+                    range: 7..8,
+                    expected: ExpectedToken::Alias,
+                },
+            ),
+            (
+                "%{name:a:}",
+                GrokPatternError::InvalidPatternDefinition {
+                    range: 9..10,
+                    expected: ExpectedToken::Extract,
+                },
+            ),
+            (
+                "%{name:a:b:c}",
+                GrokPatternError::InvalidPattern {
+                    range: 10..11,
+                    expected: EXTRACT_END,
+                },
+            ),
+            (
+                "%{name=}",
+                GrokPatternError::InvalidPatternDefinition {
+                    range: 7..8,
+                    expected: ExpectedToken::Definition,
+                },
+            ),
+            (
+                "%{name:=a}",
+                GrokPatternError::InvalidPatternDefinition {
+                    range: 7..8,
+                    expected: ExpectedToken::Alias,
+                },
+            ),
+            (
+                "%{name=a{2}",
+                GrokPatternError::InvalidPatternDefinition {
+                    range: 7..11,
+                    expected: ExpectedToken::OneOf(&['}']),
+                },
+            ),
+        ] {
+            let error = grok_split(pattern).find_map(|c| match c {
+                GrokComponent::PatternError(e) => Some(e),
+                _ => None,
+            });
+            assert_eq!(Some(expected), error, "{pattern}");
+        }
+
+        let Some(GrokComponent::PatternError(error)) = grok_split("%{na.me}").next() else {
+            panic!("should have failed");
+        };
+        assert_eq!(
+            "invalid character '.' (expected one of ':', '=' or '}')",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn error_recovery() {
+        let pattern = "%{A!} %{B} %{C:}%{D=x %{E";
+        let components = grok_split(pattern)
+            .with_recovery()
+            .map(|c| match c {
+                GrokComponent::PatternError(e) => format!("<{:?}>", e.range()),
+                c => c.to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["<3..4>", " ", "%{B}", " ", "<15..16>", "<20..25>",],
+            components
+        );
+
+        // Without recovery, the first error ends the components.
+        assert_eq!(1, grok_split(pattern).count());
+    }
+
+    #[test]
+    fn escaped_grok_patterns() {
+        for (pattern, expected) in [
+            (r"\%{", vec![r#"<escape "\\%{">"#]),
+            (
+                r"a\%{WORD}",
+                vec![r#""a""#, r#"<escape "\\%{">"#, r#""WORD}""#],
+            ),
+            (
+                r"\\%{WORD}",
+                vec![
+                    r#""\\\\""#,
+                    r#"%{ name="WORD" alias="" capture="" definition="" }"#,
+                ],
+            ),
+            (r"a\%b", vec![r#""a\\%b""#]),
+            ("100%%", vec![r#""100""#, r#""%""#, r#""%""#]),
+            (
+                r"\%{%{A}",
+                vec![
+                    r#"<escape "\\%{">"#,
+                    r#"%{ name="A" alias="" capture="" definition="" }"#,
+                ],
+            ),
+            // Like in Logstash, `%%{` is a literal `%` followed by a pattern
+            (
+                "%%{WORD}",
+                vec![
+                    r#""%""#,
+                    r#"%{ name="WORD" alias="" capture="" definition="" }"#,
+                ],
+            ),
+            (
+                r"%{A=\%{x}",
+                vec![r#"%{ name="A" alias="" capture="" definition="\\%{x" }"#],
+            ),
+        ] {
+            let components = grok_split(pattern).collect::<Vec<_>>();
+            assert_eq!(
+                expected,
+                components
+                    .iter()
+                    .map(|c| format!("{c:?}"))
+                    .collect::<Vec<_>>(),
+                "{pattern}"
+            );
+            let display = components.iter().map(|c| c.to_string()).collect::<String>();
+            assert_eq!(pattern, display);
+        }
+
+        let Some(GrokComponent::Escape { range, string }) = grok_split(r"a\%{").nth(1) else {
+            panic!("should have parsed an escape");
+        };
+        assert_eq!((1..4, r"\%{"), (range, string));
+    }
+
     #[test]
     fn real_grok_patterns() {
         for pattern in &[
@@ -364,6 +859,13 @@ mod tests {
             "%{na.me:a:b}",
             "%{name:a:b:c}",
             "%{name:a:b:c:d}",
+            "%{ns::name::b:c}",
+            "%{ns::name:a::b}",
+            "%{::name}",
+            "%{name=a{3}",
+            "%{name={}",
+            r"%{name=\}",
+            r"%{name=a\\\}",
         ] {
             eprintln!("{pattern} -> {:?}", grok_split(pattern).collect::<Vec<_>>());
 
@@ -1,6 +1,7 @@
 use crate::Error;
 use pcre2::bytes::{Captures, Regex, RegexBuilder};
 use std::collections::{btree_map, BTreeMap, HashMap};
+use std::ops::Range;
 
 pub(crate) const ENGINE: crate::Engine = crate::Engine::Pcre2;
 
@@ -8,7 +9,7 @@ pub(crate) const ENGINE: crate::Engine = crate::Engine::Pcre2;
 #[derive(Debug)]
 pub(crate) struct Pcre2Pattern {
     pub regex: Regex,
-    pub names: BTreeMap<String, usize>,
+    pub names: BTreeMap<String, Vec<usize>>,
 }
 
 impl Pcre2Pattern {
@@ -20,13 +21,11 @@ impl Pcre2Pattern {
         builder.utf(true);
         match builder.build(regex) {
             Ok(r) => Ok({
-                let mut names = BTreeMap::new();
-                for (i, name) in r.capture_names().iter().enumerate() {
-                    if let Some(name) = name {
-                        let name = alias.get(name).map_or(name, |s| s).to_string();
-                        names.insert(name, i);
-                    }
-                }
+                let groups = r.capture_names().iter().enumerate();
+                let names = crate::group_names(
+                    groups.filter_map(|(i, name)| Some((i, name.as_deref()?))),
+                    alias,
+                );
                 Self { regex: r, names }
             }),
             Err(e) => Err(Error::RegexCompilationFailed(format!(
@@ -47,6 +46,18 @@ impl Pcre2Pattern {
             })
     }
 
+    /// Returns whether the pattern matches somewhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text.as_bytes()).unwrap_or_default()
+    }
+
+    /// Returns the span of the first match in the text, without resolving the
+    /// capture groups.
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        let m = self.regex.find(text.as_bytes()).ok().flatten()?;
+        Some(m.start()..m.end())
+    }
+
     /// Returns all names this `Pattern` captures.
     pub fn capture_names(&self) -> impl Iterator<Item = &str> {
         self.names.keys().map(|s| s.as_str())
@@ -65,11 +76,23 @@ impl<'a> Pcre2Matches<'a> {
     pub fn get(&self, name_or_alias: &str) -> Option<&str> {
         self.pattern
             .names
-            .get(name_or_alias)
-            .and_then(|&idx| self.captures.get(idx))
+            .get(name_or_alias)?
+            .iter()
+            .rev()
+            .find_map(|&idx| self.captures.get(idx))
             .map(|m| std::str::from_utf8(m.as_bytes()).unwrap())
     }
 
+    /// Gets the values of every group with the name (or) alias that took part
+    /// in the match, along with their index.
+    pub fn get_all(&self, name_or_alias: &str) -> Vec<(usize, &str)> {
+        let indices = self.pattern.names.get(name_or_alias).into_iter().flatten();
+        indices
+            .filter_map(|&idx| Some((idx, self.captures.get(idx)?)))
+            .map(|(idx, m)| (idx, std::str::from_utf8(m.as_bytes()).unwrap()))
+            .collect()
+    }
+
     /// Returns a tuple of key/value with all the matches found.
     ///
     /// Note that if no match is found, the value is empty.
@@ -93,15 +116,15 @@ impl<'a> IntoIterator for &'a Pcre2Matches<'a> {
 /// An `Iterator` over all matches, accessible via `Matches`.
 pub(crate) struct Pcre2MatchesIter<'a> {
     captures: &'a Captures<'a>,
-    names: btree_map::Iter<'a, String, usize>,
+    names: btree_map::Iter<'a, String, Vec<usize>>,
 }
 
 impl<'a> Iterator for Pcre2MatchesIter<'a> {
     type Item = (&'a str, &'a str);
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (k, &v) in self.names.by_ref() {
-            if let Some(m) = self.captures.get(v) {
+        for (k, v) in self.names.by_ref() {
+            if let Some(m) = v.iter().rev().find_map(|&idx| self.captures.get(idx)) {
                 return Some((k.as_str(), std::str::from_utf8(m.as_bytes()).unwrap()));
             }
         }
@@ -0,0 +1,176 @@
+//! The fields captured by a pattern, see [`Pattern::schema`].
+
+use std::collections::HashMap;
+
+use crate::ast::{self, visit_children, Group, GroupKind, Node, Visitor};
+use crate::tree::Capture;
+use crate::Pattern;
+
+/// A field captured by a [`Pattern`], as returned by [`Pattern::schema`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldSchema {
+    name: String,
+    pattern: Option<String>,
+    optional: bool,
+    extract: Option<String>,
+    path: Vec<String>,
+}
+
+impl FieldSchema {
+    /// Returns the name the field is captured as.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the pattern of the placeholder capturing the
+    /// field, or `None` for a named group of the regular expression.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    /// Returns whether the field may be absent from a match, because it is
+    /// inside an optional or repeated group, or in one of the alternatives of
+    /// an alternation.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Returns the extract of the placeholder, such as `int` in
+    /// `%{NUMBER:size:int}`.
+    pub fn extract(&self) -> Option<&str> {
+        self.extract.as_deref()
+    }
+
+    /// Returns the names of the patterns the field was found in, from the
+    /// outermost one.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+}
+
+/// Collects the fields of a pattern, in the order they appear in it.
+pub(crate) fn fields(pattern: &Pattern) -> Vec<FieldSchema> {
+    let mut fields = Fields {
+        pattern,
+        groups: (0..pattern.captures.len())
+            .filter(|&index| pattern.captures[index].key.is_some())
+            .collect(),
+        fields: vec![],
+        indices: HashMap::new(),
+        enclosing: None,
+        optional: false,
+    };
+    match ast::parse(pattern.regex_source()) {
+        Ok(ast) => ast.nodes.iter().for_each(|node| fields.visit(node)),
+        // Without the structure of the regex, every field may be absent
+        Err(_) => {
+            fields.optional = true;
+            for group in 0..fields.groups.len() {
+                fields.add(&format!("_n_{group}"));
+            }
+        }
+    }
+    fields.fields
+}
+
+struct Fields<'p> {
+    pattern: &'p Pattern,
+    /// The index of the placeholder of each group `_n_N`.
+    groups: Vec<usize>,
+    fields: Vec<FieldSchema>,
+    indices: HashMap<String, usize>,
+    /// The placeholder of the innermost group `_n_N` being visited.
+    enclosing: Option<usize>,
+    optional: bool,
+}
+
+impl Fields<'_> {
+    /// Adds the field of a named group, returning its placeholder.
+    fn add(&mut self, group: &str) -> Option<usize> {
+        let placeholder = group
+            .strip_prefix("_n_")
+            .and_then(|index| self.groups.get(index.parse::<usize>().ok()?))
+            .copied();
+        let field = match placeholder {
+            Some(index) => {
+                let capture = &self.pattern.captures[index];
+                let name = capture.key.clone().unwrap_or_default();
+                FieldSchema {
+                    extract: self.pattern.get_extract(&name).map(str::to_string),
+                    pattern: Some(capture.pattern.clone()),
+                    path: self.path(capture.parent),
+                    optional: self.optional,
+                    name,
+                }
+            }
+            None => FieldSchema {
+                name: group.to_string(),
+                pattern: None,
+                optional: self.optional,
+                extract: None,
+                path: self.path(self.enclosing),
+            },
+        };
+
+        // Several groups with the same name make a field that is only absent
+        // when all of them are
+        match self.indices.get(&field.name) {
+            Some(&index) => self.fields[index].optional &= field.optional,
+            None => {
+                self.indices.insert(field.name.clone(), self.fields.len());
+                self.fields.push(field);
+            }
+        }
+        placeholder
+    }
+
+    /// Returns the names of the patterns of a placeholder and of those it was
+    /// found in, from the outermost one.
+    fn path(&self, mut placeholder: Option<usize>) -> Vec<String> {
+        let captures: &[Capture] = &self.pattern.captures;
+        let mut path = vec![];
+        while let Some(index) = placeholder {
+            path.push(captures[index].pattern.clone());
+            placeholder = captures[index].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    fn visit_optional(&mut self, node: &Node<'_>) {
+        let optional = std::mem::replace(&mut self.optional, true);
+        visit_children(self, node);
+        self.optional = optional;
+    }
+}
+
+impl<'a> Visitor<'a> for Fields<'_> {
+    fn visit(&mut self, node: &Node<'a>) {
+        match node {
+            Node::Repeat { quantifier, .. }
+                if quantifier.starts_with(['?', '*'])
+                    || quantifier.starts_with("{0")
+                    || quantifier.starts_with("{,") =>
+            {
+                self.visit_optional(node)
+            }
+            Node::Alternation(alternatives) if alternatives.len() > 1 => self.visit_optional(node),
+            Node::Group(Group {
+                kind: GroupKind::NegativeLookAhead | GroupKind::NegativeLookBehind,
+                ..
+            }) => self.visit_optional(node),
+            Node::Group(Group {
+                kind: GroupKind::Named(name),
+                ..
+            }) => {
+                let enclosing = self.enclosing;
+                if let Some(placeholder) = self.add(name) {
+                    self.enclosing = Some(placeholder);
+                }
+                visit_children(self, node);
+                self.enclosing = enclosing;
+            }
+            node => visit_children(self, node),
+        }
+    }
+}
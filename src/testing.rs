@@ -0,0 +1,426 @@
+//! A harness to test grok patterns against sample inputs.
+//!
+//! Tests are written as annotations in `.pattern` files, directly after the
+//! definitions they test. The annotations are comments, so they are ignored
+//! by anything that only loads the definitions:
+//!
+//! ```text
+//! SYSLOGFACILITY <%{NONNEGINT:facility}.%{NONNEGINT:priority}>
+//! #test SYSLOGFACILITY <4.6>
+//! #expect facility=4
+//! #expect priority=6
+//! #test! SYSLOGFACILITY <4>
+//! ```
+//!
+//! - `#test NAME input` asserts that `^%{NAME}$` matches the input, which is
+//!   everything after the space following `NAME`.
+//! - `#test! NAME input` asserts that `^%{NAME}$` does not match the input.
+//! - `#expect key=value` asserts that the preceding test captured `value` for
+//!   `key`, and `#expect key` asserts that `key` was not captured.
+//!
+//! The patterns are compiled with all named patterns captured, so expectations
+//! may refer to pattern names as well as aliases.
+
+use crate::{Error, Grok, Pattern};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A single test of a pattern against a sample input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternTest {
+    /// The line of the `#test` annotation, starting from 1.
+    pub line: usize,
+    /// The name of the pattern under test.
+    pub name: String,
+    /// The sample input.
+    pub input: String,
+    /// Whether the input is expected to match.
+    pub should_match: bool,
+    /// The expected captures, where `None` expects the capture to be absent.
+    pub expected: Vec<(String, Option<String>)>,
+}
+
+impl PatternTest {
+    /// Runs this test against the patterns of `grok`.
+    pub fn run(&self, grok: &Grok) -> Result<(), FailureReason> {
+        let pattern = self.compile(grok).map_err(FailureReason::Compile)?;
+        self.run_compiled(&pattern)
+    }
+
+    fn compile(&self, grok: &Grok) -> Result<Pattern, Error> {
+        grok.compile(&format!("^%{{{}}}$", self.name), false)
+    }
+
+    fn run_compiled(&self, pattern: &Pattern) -> Result<(), FailureReason> {
+        let Some(matches) = pattern.match_against(&self.input) else {
+            return if self.should_match {
+                Err(FailureReason::NoMatch)
+            } else {
+                Ok(())
+            };
+        };
+        if !self.should_match {
+            return Err(FailureReason::UnexpectedMatch);
+        }
+
+        for (key, expected) in &self.expected {
+            let actual = matches.get(key);
+            if actual != expected.as_deref() {
+                return Err(FailureReason::Capture {
+                    name: key.clone(),
+                    expected: expected.clone(),
+                    actual: actual.map(str::to_string),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why a `PatternTest` failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FailureReason {
+    /// The pattern failed to compile.
+    Compile(Error),
+    /// The input was expected to match, but did not.
+    NoMatch,
+    /// The input was expected not to match, but did.
+    UnexpectedMatch,
+    /// A capture did not have the expected value.
+    Capture {
+        /// The name of the capture.
+        name: String,
+        /// The expected value, or `None` if it was expected to be absent.
+        expected: Option<String>,
+        /// The actual value, or `None` if it was absent.
+        actual: Option<String>,
+    },
+}
+
+/// A failed `PatternTest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternTestFailure {
+    /// The test that failed.
+    pub test: PatternTest,
+    /// Why the test failed.
+    pub reason: FailureReason,
+}
+
+impl fmt::Display for PatternTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: %{{{}}} on {:?}: ",
+            self.test.line, self.test.name, self.test.input
+        )?;
+        match &self.reason {
+            FailureReason::Compile(e) => write!(f, "{e}"),
+            FailureReason::NoMatch => f.write_str("expected a match"),
+            FailureReason::UnexpectedMatch => f.write_str("expected no match"),
+            FailureReason::Capture {
+                name,
+                expected,
+                actual,
+            } => write!(f, "expected {name} to be {expected:?}, was {actual:?}"),
+        }
+    }
+}
+
+/// The results of running the tests of a `PatternFile`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatternTestReport {
+    passed: usize,
+    failures: Vec<PatternTestFailure>,
+}
+
+impl PatternTestReport {
+    /// Returns the number of tests that passed.
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    /// Returns the tests that failed.
+    pub fn failures(&self) -> &[PatternTestFailure] {
+        &self.failures
+    }
+
+    /// Returns true if no test failed.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// An error in the syntax of a pattern file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternFileError {
+    /// The line of the error, starting from 1.
+    pub line: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for PatternFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PatternFileError {}
+
+/// The definitions and tests of a `.pattern` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatternFile {
+    definitions: Vec<(String, String)>,
+    tests: Vec<PatternTest>,
+}
+
+impl PatternFile {
+    /// Parses the contents of a `.pattern` file.
+    pub fn parse(source: &str) -> Result<Self, PatternFileError> {
+        let mut file = PatternFile::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| PatternFileError {
+                line: line_number,
+                message: message.to_string(),
+            };
+
+            if let Some(test) = line.strip_prefix("#test") {
+                let (should_match, test) = match test.strip_prefix('!') {
+                    Some(test) => (false, test),
+                    None => (true, test),
+                };
+                let Some(test) = test.strip_prefix(' ') else {
+                    return Err(error("expected a pattern name after #test"));
+                };
+                let (name, input) = test.split_once(' ').unwrap_or((test, ""));
+                if name.is_empty() {
+                    return Err(error("expected a pattern name after #test"));
+                }
+                file.tests.push(PatternTest {
+                    line: line_number,
+                    name: name.to_string(),
+                    input: input.to_string(),
+                    should_match,
+                    expected: vec![],
+                });
+            } else if let Some(expect) = line.strip_prefix("#expect ") {
+                let Some(test) = file.tests.last_mut() else {
+                    return Err(error("#expect must follow a #test"));
+                };
+                if !test.should_match {
+                    return Err(error("#expect cannot follow a #test!"));
+                }
+                let expected = match expect.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (expect.to_string(), None),
+                };
+                test.expected.push(expected);
+            } else if line.starts_with('#') || line.is_empty() {
+                continue;
+            } else {
+                let Some((name, definition)) = line.split_once(' ') else {
+                    return Err(error("expected a pattern name and definition"));
+                };
+                file.definitions
+                    .push((name.to_string(), definition.to_string()));
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Returns the pattern definitions, in file order.
+    pub fn definitions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.definitions
+            .iter()
+            .map(|(name, definition)| (name.as_str(), definition.as_str()))
+    }
+
+    /// Returns the pattern tests, in file order.
+    pub fn tests(&self) -> &[PatternTest] {
+        &self.tests
+    }
+
+    /// Adds the pattern definitions to `grok`.
+    pub fn add_to(&self, grok: &mut Grok) {
+        for (name, definition) in self.definitions() {
+            grok.add_pattern(name, definition);
+        }
+    }
+
+    /// Runs every test against the patterns of `grok`, which should already
+    /// contain the definitions of this file (see [`PatternFile::add_to`]).
+    pub fn run(&self, grok: &Grok) -> PatternTestReport {
+        let mut report = PatternTestReport::default();
+        let mut compiled = HashMap::new();
+
+        for test in &self.tests {
+            let pattern = compiled
+                .entry(test.name.as_str())
+                .or_insert_with(|| test.compile(grok));
+            let result = match pattern {
+                Ok(pattern) => test.run_compiled(pattern),
+                Err(e) => Err(FailureReason::Compile(e.clone())),
+            };
+            match result {
+                Ok(()) => report.passed += 1,
+                Err(reason) => report.failures.push(PatternTestFailure {
+                    test: test.clone(),
+                    reason,
+                }),
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PatternSet;
+
+    #[test]
+    fn test_parse() {
+        let file = PatternFile::parse(
+            "# A comment\nWORD \\b\\w+\\b\n#test WORD hello\n#expect WORD=hello\n#expect other\n\n#test! WORD two words\n#test WORD \n",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![("WORD", r"\b\w+\b")],
+            file.definitions().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                PatternTest {
+                    line: 3,
+                    name: "WORD".to_string(),
+                    input: "hello".to_string(),
+                    should_match: true,
+                    expected: vec![
+                        ("WORD".to_string(), Some("hello".to_string())),
+                        ("other".to_string(), None),
+                    ],
+                },
+                PatternTest {
+                    line: 7,
+                    name: "WORD".to_string(),
+                    input: "two words".to_string(),
+                    should_match: false,
+                    expected: vec![],
+                },
+                PatternTest {
+                    line: 8,
+                    name: "WORD".to_string(),
+                    input: "".to_string(),
+                    should_match: true,
+                    expected: vec![],
+                },
+            ],
+            file.tests()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (source, line) in [
+            ("#expect a=b", 1),
+            ("#test\n", 1),
+            ("#test \n", 1),
+            ("A a\n#test! A b\n#expect a=b", 3),
+            ("A a\nNODEFINITION", 2),
+        ] {
+            let err = PatternFile::parse(source).unwrap_err();
+            assert_eq!(line, err.line, "{source:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let file = PatternFile::parse(
+            "KV %{WORD:key}=%{WORD:value}\n\
+             #test KV a=b\n\
+             #expect key=a\n\
+             #expect value=b\n\
+             #test KV a=c\n\
+             #expect value=b\n\
+             #test! KV a=b\n\
+             #test KV a b\n\
+             #test MISSING a\n",
+        )
+        .unwrap();
+        let mut grok = Grok::empty();
+        grok.add_pattern("WORD", r"\w+");
+        file.add_to(&mut grok);
+
+        let report = file.run(&grok);
+        assert!(!report.is_success());
+        assert_eq!(1, report.passed());
+        let reasons = report
+            .failures()
+            .iter()
+            .map(|f| (f.test.line, f.reason.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    5,
+                    FailureReason::Capture {
+                        name: "value".to_string(),
+                        expected: Some("b".to_string()),
+                        actual: Some("c".to_string()),
+                    }
+                ),
+                (7, FailureReason::UnexpectedMatch),
+                (8, FailureReason::NoMatch),
+                (
+                    9,
+                    FailureReason::Compile(Error::DefinitionNotFound("MISSING".to_string()))
+                ),
+            ],
+            reasons
+        );
+        assert_eq!(
+            r#"line 8: %{KV} on "a b": expected a match"#,
+            report.failures()[2].to_string()
+        );
+        assert_eq!(Ok(()), file.tests()[0].run(&grok));
+    }
+
+    /// Runs the tests embedded in every built-in pattern file of a set.
+    fn run_builtin_patterns(set: PatternSet, files: &[(&str, &str)]) {
+        let grok = Grok::with_pattern_set(set);
+        let mut passed = 0;
+        let mut failures = vec![];
+        for (file_name, source) in files {
+            let file = PatternFile::parse(source).unwrap_or_else(|e| panic!("{file_name}: {e}"));
+            for should_match in [true, false] {
+                assert!(
+                    file.tests().iter().any(|t| t.should_match == should_match),
+                    "{file_name}.pattern has no {} annotation",
+                    if should_match { "#test" } else { "#test!" }
+                );
+            }
+            let report = file.run(&grok);
+            passed += report.passed();
+            for failure in report.failures() {
+                failures.push(format!("{file_name}.pattern {failure}"));
+            }
+        }
+        assert!(passed > 0);
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_builtin_patterns() {
+        run_builtin_patterns(PatternSet::Legacy, crate::PATTERN_FILES);
+    }
+
+    #[test]
+    fn test_builtin_ecs_v1_patterns() {
+        run_builtin_patterns(PatternSet::EcsV1, crate::ECS_V1_PATTERN_FILES);
+    }
+}
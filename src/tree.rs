@@ -0,0 +1,110 @@
+//! The tree of the placeholders that matched, see [`Matches::tree`].
+
+use std::ops::Range;
+
+use crate::Matches;
+
+/// A placeholder of an expanded pattern, in expansion order. Those that
+/// capture are expanded into the named groups `_n_N`, in the same order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Capture {
+    /// The name of the pattern, as written in the placeholder.
+    pub pattern: String,
+    /// The alias of the placeholder, empty if it has none.
+    pub alias: String,
+    /// The name the value is captured as, if the placeholder captures.
+    pub key: Option<String>,
+    /// The index of the placeholder this one was found in.
+    pub parent: Option<usize>,
+}
+
+/// A placeholder that took part in a match, as returned by
+/// [`Matches::tree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchNode<'a> {
+    pattern: &'a str,
+    alias: Option<&'a str>,
+    name: &'a str,
+    span: Range<usize>,
+    value: &'a str,
+    children: Vec<MatchNode<'a>>,
+}
+
+impl<'a> MatchNode<'a> {
+    /// Returns the name of the pattern of the placeholder, such as `IPV4`, or
+    /// `namespace::NAME` when it was referred to in a namespace.
+    pub fn pattern(&self) -> &'a str {
+        self.pattern
+    }
+
+    /// Returns the alias of the placeholder, if it has one.
+    pub fn alias(&self) -> Option<&'a str> {
+        self.alias
+    }
+
+    /// Returns the name the value can be looked up with in the matches.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the span of the value in the matched text.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Returns the matched value.
+    pub fn value(&self) -> &'a str {
+        self.value
+    }
+
+    /// Returns the placeholders of the pattern that took part in the match,
+    /// in the order they appear in the pattern.
+    pub fn children(&self) -> &[MatchNode<'a>] {
+        &self.children
+    }
+}
+
+/// Builds the tree of the placeholders that took part in the match.
+pub(crate) fn build<'a>(matches: &Matches<'a>, captures: &'a [Capture]) -> Vec<MatchNode<'a>> {
+    let text = matches.text;
+    let mut nodes: Vec<Option<MatchNode<'a>>> = captures
+        .iter()
+        .map(|capture| {
+            let key = capture.key.as_deref()?;
+            let value = matches.get(key)?;
+            let start = value.as_ptr() as usize - text.as_ptr() as usize;
+            let span = start..start + value.len();
+            Some(MatchNode {
+                pattern: &capture.pattern,
+                alias: Some(capture.alias.as_str()).filter(|alias| !alias.is_empty()),
+                name: key,
+                value: &text[span.clone()],
+                span,
+                children: vec![],
+            })
+        })
+        .collect();
+
+    // Children come after their parent, so every node is complete by the time
+    // it is moved into its parent. They are collected in reverse order.
+    let mut roots = vec![];
+    for index in (0..captures.len()).rev() {
+        let Some(mut node) = nodes[index].take() else {
+            continue;
+        };
+        node.children.reverse();
+        let mut parent = captures[index].parent;
+        while let Some(index) = parent {
+            if nodes[index].is_some() {
+                break;
+            }
+            parent = captures[index].parent;
+        }
+        match parent.and_then(|index| nodes[index].as_mut()) {
+            Some(parent) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+    roots.reverse();
+    roots
+}